@@ -1,3 +1,40 @@
 pub const CHUNK_SIZE_X: usize = 50;
 pub const CHUNK_SIZE_Y: usize = 50;
 pub const CHUNK_SIZE: usize = CHUNK_SIZE_X * CHUNK_SIZE_Y;
+
+pub const MATERIALS_PATH: &str = "assets/materials.toml";
+pub const WORLD_PATH: &str = "assets/world.toml";
+
+/// Temperature of empty space
+pub const AMBIENT_TEMPERATURE: f32 = 20.0;
+/// Fraction of the temperature difference that flows between
+/// two perfectly conducting tiles in one tick
+pub const HEAT_FLOW_RATE: f32 = 0.2;
+
+/// Falling speed gained in one tick, in tiles per tick
+pub const GRAVITY: f32 = 0.5;
+/// Highest falling speed, in tiles per tick
+pub const TERMINAL_VELOCITY: f32 = 8.0;
+
+/// Most tiles pressure moves within one liquid body in one tick
+pub const PRESSURE_MOVES: usize = 4;
+
+/// Ticks a tile stays charged, it can't take a new charge until they have passed,
+/// below 3 charges would flow back into the tiles they came from
+pub const CHARGE_TICKS: u8 = 3;
+
+/// Speed of tiles flung from the center of a blast, in tiles per tick
+pub const BLAST_SPEED: f32 = 6.0;
+/// Radius of blasts set off by hand
+pub const TOOL_BLAST_RADIUS: f32 = 10.0;
+
+/// Radius and strength of wind painted by hand
+pub const TOOL_WIND_RADIUS: f32 = 8.0;
+pub const TOOL_WIND_FORCE: f32 = 1.0;
+/// Fraction of the painted wind that is left after one tick
+pub const WIND_DECAY: f32 = 0.998;
+/// Weakest wind that doesn't die down completely
+pub const MIN_WIND: f32 = 0.01;
+
+/// Chance of a burning tile emitting smoke in one tick
+pub const SMOKE_CHANCE: f32 = 0.1;
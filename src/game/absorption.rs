@@ -0,0 +1,75 @@
+use rayon::prelude::*;
+use std::collections::HashSet;
+
+use crate::constants::AMBIENT_TEMPERATURE;
+
+use super::{calculator::ViewUpdates, random::RandomStream, World};
+
+impl World {
+    /// Lets porous tiles soak up neighbouring liquid, saturated tiles change their material
+    pub fn absorb(&mut self, view_update: &mut ViewUpdates) {
+        // Find porous tiles next to liquid in parallel
+        let materials = &self.materials;
+        let chunks = &self.chunks;
+        let mut candidates = chunks
+            .par_iter()
+            .flat_map(|(_, chunk)| chunk.absorption_candidates(chunks, materials))
+            .collect::<Vec<_>>();
+        candidates.sort_by_key(|(tile, absorbed)| {
+            let (position, absorbed_position) =
+                (tile.global_position(), absorbed.global_position());
+            (
+                position.x,
+                position.y,
+                absorbed_position.x,
+                absorbed_position.y,
+            )
+        });
+        candidates.dedup();
+
+        // Every tile absorbs or is absorbed at most once per tick
+        let mut absorbed_tiles = HashSet::new();
+        for (tile, absorbed) in candidates {
+            if absorbed_tiles.contains(&tile) || absorbed_tiles.contains(&absorbed) {
+                continue;
+            }
+
+            let absorption = match (self.material_at(tile), self.material_at(absorbed)) {
+                (Some(material), Some(absorbed_material)) => {
+                    match self.materials.get(material).absorption {
+                        Some(absorption) if absorption.absorbs == absorbed_material => absorption,
+                        _ => continue,
+                    }
+                }
+                _ => continue,
+            };
+
+            let roll = self
+                .random
+                .tile(tile.global_position(), RandomStream::Absorption)
+                .with(absorbed.global_position())
+                .next_f32();
+            if roll >= absorption.chance {
+                // Try again next tick
+                self.chunks
+                    .get_mut(&tile.chunk_pos)
+                    .unwrap()
+                    .queue_update(tile.index);
+                continue;
+            }
+
+            absorbed_tiles.insert(tile);
+            absorbed_tiles.insert(absorbed);
+            self.replace_tile(absorbed, None, AMBIENT_TEMPERATURE, view_update);
+
+            let chunk = self.chunks.get_mut(&tile.chunk_pos).unwrap();
+            chunk.layers.saturation[tile.index] += 1;
+            if chunk.layers.saturation[tile.index] >= absorption.capacity {
+                let temperature = chunk.layers.temperature[tile.index];
+                self.replace_tile(tile, Some(absorption.into), temperature, view_update);
+            } else {
+                chunk.queue_update(tile.index);
+            }
+        }
+    }
+}
@@ -0,0 +1,295 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use macroquad::prelude::{ivec2, IVec2};
+use rayon::prelude::*;
+
+use super::{
+    chunk::Chunk,
+    gravity::{Gravity, GravityField},
+    material::{MaterialRegistry, MovementRule},
+    tile::{BodyId, Tile, TileInfo},
+    World,
+};
+
+impl World {
+    pub fn new_body(&mut self) -> BodyId {
+        self.next_body += 1;
+        self.next_body - 1
+    }
+
+    /// Splits bodies that have been cut apart into their connected pieces,
+    /// resting rigid tiles that aren't welded into a body get a body of their own
+    /// and tiles that are no longer rigid leave their body
+    pub fn split_bodies(&mut self) {
+        let materials = &self.materials;
+        let mut tiles = self
+            .chunks
+            .par_iter()
+            .flat_map_iter(|(&chunk_pos, chunk)| {
+                chunk.tiles().filter_map(move |(index, tile_info)| {
+                    let tile_info = tile_info.as_ref()?;
+                    let rigid = materials.get(tile_info.material).movement == MovementRule::Rigid;
+                    let body = chunk.layers.body[index];
+                    (rigid || body.is_some()).then(|| {
+                        let tile = Tile { chunk_pos, index };
+                        (tile.global_position(), body, rigid)
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+        tiles.sort_by_key(|&(position, ..)| (position.x, position.y));
+
+        let mut bodies = BTreeMap::<BodyId, HashSet<IVec2>>::new();
+        for (position, body, rigid) in tiles {
+            match (body, rigid) {
+                (Some(body), true) => {
+                    bodies.entry(body).or_default().insert(position);
+                }
+                (Some(_), false) => self.weld(position, None),
+                (None, _) => {
+                    if !self.is_flying(position) {
+                        let body = self.new_body();
+                        self.weld(position, Some(body));
+                    }
+                }
+            }
+        }
+
+        // The first piece keeps the body, the others get new ones
+        for (_, mut members) in bodies {
+            let mut first = true;
+            while let Some(&start) = members
+                .iter()
+                .min_by_key(|position| (position.x, position.y))
+            {
+                members.remove(&start);
+                let mut piece = vec![start];
+                let mut index = 0;
+                while index < piece.len() {
+                    for shift in &[ivec2(1, 0), ivec2(-1, 0), ivec2(0, 1), ivec2(0, -1)] {
+                        if members.remove(&(piece[index] + *shift)) {
+                            piece.push(piece[index] + *shift);
+                        }
+                    }
+                    index += 1;
+                }
+
+                if !first {
+                    let body = self.new_body();
+                    for position in piece {
+                        self.weld(position, Some(body));
+                    }
+                }
+                first = false;
+            }
+        }
+    }
+
+    fn is_flying(&self, position: IVec2) -> bool {
+        let tile = Tile::from_global_position(position);
+        self.chunks[&tile.chunk_pos].layers.flight[tile.index].is_some()
+    }
+
+    fn weld(&mut self, position: IVec2, body: Option<BodyId>) {
+        let tile = Tile::from_global_position(position);
+        let chunk = self.chunks.get_mut(&tile.chunk_pos).unwrap();
+        chunk.layers.body[tile.index] = body;
+        chunk.queue_update(tile.index);
+    }
+}
+
+/// Lets rigid bodies fall by one tile, a body only moves if all of its tiles can,
+/// returns the tiles that have changed
+///
+/// Bodies move before the calculation instead of through its dependencies, which
+/// resolve one tile at a time and can't make the moves of many tiles depend on each other,
+/// the tiles a body leaves, enters and displaces are queued, so the calculation
+/// that follows sees the body at its new position
+pub fn move_bodies(
+    chunks: &mut HashMap<IVec2, &mut Chunk>,
+    materials: &MaterialRegistry,
+    gravity: &GravityField,
+) -> Vec<Tile> {
+    let mut bodies = BTreeMap::<BodyId, Vec<IVec2>>::new();
+    for (body, position) in chunks
+        .par_iter()
+        .flat_map(|(&chunk_pos, chunk)| {
+            chunk
+                .body_tiles()
+                .into_par_iter()
+                .map(move |(index, body)| (body, Tile { chunk_pos, index }.global_position()))
+        })
+        .collect::<Vec<_>>()
+    {
+        bodies.entry(body).or_default().push(position);
+    }
+
+    // A body falls along the gravity at its first tile
+    let mut bodies = bodies
+        .into_iter()
+        .map(|(body, mut members)| {
+            members.sort_by_key(|position| (position.x, position.y));
+            let gravity = gravity.at(members[0]);
+            members.sort_by_key(|&position| (gravity.line(position), gravity.height(position)));
+            (body, gravity, members)
+        })
+        .collect::<Vec<_>>();
+
+    // Lower bodies move first, so the ones resting on them can follow
+    bodies.sort_by_key(|(body, gravity, members)| {
+        let height = members
+            .iter()
+            .map(|&position| gravity.height(position))
+            .min();
+        (height, *body)
+    });
+
+    let mut changed = Vec::new();
+    for (_, gravity, members) in bodies {
+        if let Some(displaced) = body_fall(chunks, materials, gravity, &members) {
+            changed.extend(shift_body(chunks, gravity, &members, displaced));
+        }
+    }
+    changed
+}
+
+fn tile_at<'a>(
+    chunks: &'a HashMap<IVec2, &mut Chunk>,
+    position: IVec2,
+) -> Option<&'a Option<TileInfo>> {
+    let tile = Tile::from_global_position(position);
+    chunks
+        .get(&tile.chunk_pos)
+        .map(|chunk| &chunk.tile_info[tile.index])
+}
+
+/// Checks whether a body can fall, returns the fluid tiles it has to displace
+/// together with the tiles they are moved to
+fn body_fall(
+    chunks: &HashMap<IVec2, &mut Chunk>,
+    materials: &MaterialRegistry,
+    gravity: Gravity,
+    members: &[IVec2],
+) -> Option<Vec<(IVec2, IVec2)>> {
+    let density = members
+        .iter()
+        .filter_map(|&position| tile_at(chunks, position)?.as_ref())
+        .map(|tile_info| materials.get(tile_info.material).density)
+        .fold(0.0, f32::max);
+
+    // Every run of the body along gravity enters a tile below it and leaves one on top,
+    // members are sorted by line along gravity, then from bottom to top
+    let down = gravity.direction();
+    let mut displaced = Vec::new();
+    let mut runs = members.iter().peekable();
+    while let Some(&bottom) = runs.next() {
+        let mut top = bottom;
+        while let Some(&&next) = runs.peek() {
+            if next != top - down {
+                break;
+            }
+            top = next;
+            runs.next();
+        }
+
+        let below = bottom + down;
+        match tile_at(chunks, below)? {
+            None => {}
+            Some(tile_info) => {
+                let material = materials.get(tile_info.material);
+                if !material.movement.is_fluid() || material.density >= density {
+                    return None;
+                }
+                displaced.push((below, top));
+            }
+        }
+    }
+    Some(displaced)
+}
+
+/// Moves all members of a body one tile down, displaced fluids take their place on top
+fn shift_body(
+    chunks: &mut HashMap<IVec2, &mut Chunk>,
+    gravity: Gravity,
+    members: &[IVec2],
+    displaced: Vec<(IVec2, IVec2)>,
+) -> Vec<Tile> {
+    let down = gravity.direction();
+    let mut taken = displaced;
+    taken.extend(members.iter().map(|&position| (position, position + down)));
+
+    // Take all tiles out first, so they don't overwrite each other
+    let mut moving = Vec::with_capacity(taken.len());
+    let mut extra_updates = Vec::new();
+    for (from, to) in taken {
+        let tile = Tile::from_global_position(from);
+        let chunk = chunks.get_mut(&tile.chunk_pos).unwrap();
+        let taken = chunk.tile(tile.index);
+        extra_updates.extend(chunk.set_tile(tile.index, None));
+        moving.push((tile, taken, to));
+    }
+
+    let mut changed = Vec::with_capacity(moving.len() * 2);
+    for (from, taken, to) in moving {
+        let to = Tile::from_global_position(to);
+        let chunk = chunks.get_mut(&to.chunk_pos).unwrap();
+        extra_updates.extend(chunk.set_tile(to.index, taken));
+        changed.push(from);
+        changed.push(to);
+    }
+
+    for update_tile in extra_updates {
+        if let Some(chunk) = chunks.get_mut(&update_tile.chunk_pos) {
+            chunk.queue_update(update_tile.index);
+        }
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::MATERIALS_PATH;
+
+    fn world() -> World {
+        let materials = MaterialRegistry::load(MATERIALS_PATH).unwrap();
+        World::new(materials, GravityField::default(), 0)
+    }
+
+    #[test]
+    fn pieces_of_a_cut_body_fall_on_their_own() {
+        let mut world = world();
+        // A bar resting on a pillar, the part right of the cut is no longer held by it
+        world.fill("barrier", ivec2(10, 0), ivec2(10, 9));
+        world.fill("crate", ivec2(8, 10), ivec2(14, 10));
+        let body = world.new_body();
+        for x in 8..=14 {
+            world.weld(ivec2(x, 10), Some(body));
+        }
+        world.tick();
+        world.set_tile(Tile::from_global_position(ivec2(11, 10)), None);
+
+        for _ in 0..5 {
+            world.tick();
+        }
+        let crates = world.find("crate");
+        assert!(crates.contains(&ivec2(8, 10)));
+        assert!(crates.contains(&ivec2(10, 10)));
+        assert!(crates
+            .iter()
+            .filter(|position| position.x > 11)
+            .all(|position| position.y < 10));
+    }
+
+    #[test]
+    fn rigid_tiles_without_a_body_fall() {
+        let mut world = world();
+        world.place("crate", ivec2(20, 10));
+        world.weld(ivec2(20, 10), None);
+
+        for _ in 0..5 {
+            world.tick();
+        }
+        assert!(world.find("crate")[0].y < 10);
+    }
+}
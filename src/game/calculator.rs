@@ -6,26 +6,33 @@ use std::{
 };
 
 use super::{
+    body::move_bodies,
     chunk::{
-        data_array, default_data_array, Chunk, ChunkCalculation, DataArray, Dependencies, MoveInfo,
+        data_array, Chunk, ChunkCalculation, CrossMove, CrossMoves, DataArray, Dependencies,
+        MoveInfo, MovingTile,
     },
-    tile::{Tile, TileInfo},
+    tick::TickContext,
+    tile::Tile,
 };
 
 type ChunkInformation<'a, 'b> = (
     &'b mut &'a mut Chunk,
     ChunkCalculation,
     Dependencies,
-    Option<DataArray<bool>>,             // Extra updates
-    Option<DataArray<Option<TileInfo>>>, // Cross-chunk moves
+    Option<DataArray<bool>>, // Extra updates
+    Option<CrossMoves>,      // Cross-chunk moves
 );
 
-pub type ViewUpdates = HashMap<IVec2, DataArray<Option<Option<TileInfo>>>>;
+/// Far more update cycles than a tick takes, chunks that still wait for each other
+/// after this many are stuck in a dependency cycle that hasn't been resolved
+const MAX_UPDATE_CYCLES: usize = 1000;
+
+pub type ViewUpdates = HashMap<IVec2, DataArray<Option<Option<MovingTile>>>>;
 
 pub struct Calculator {
     chunk_calculations: HashMap<IVec2, DataArray<MoveInfo>>,
     extra_updates: HashMap<IVec2, DataArray<bool>>,
-    cross_moves: HashMap<IVec2, DataArray<Option<TileInfo>>>,
+    cross_moves: HashMap<IVec2, CrossMoves>,
     calculations: HashMap<IVec2, (ChunkCalculation, Dependencies)>,
     update_queue: HashSet<IVec2>,
 }
@@ -39,7 +46,7 @@ impl Calculator {
         for chunk_pos in chunk_positions {
             update_queue.insert(chunk_pos);
             extra_updates.insert(chunk_pos, data_array(false));
-            cross_moves.insert(chunk_pos, default_data_array());
+            cross_moves.insert(chunk_pos, Vec::new());
             chunk_calculations.insert(chunk_pos, data_array(MoveInfo::Unknown));
         }
         let calculations = HashMap::with_capacity(chunk_calculations.len());
@@ -53,14 +60,30 @@ impl Calculator {
         }
     }
 
-    pub fn tick(&mut self, mut chunks: HashMap<IVec2, &mut Chunk>) -> ViewUpdates {
+    pub fn tick(
+        &mut self,
+        mut chunks: HashMap<IVec2, &mut Chunk>,
+        context: TickContext,
+    ) -> ViewUpdates {
+        // Rigid bodies move as a whole, before tiles are calculated one by one,
+        // the tiles around them are queued and calculated against their new positions
+        let body_moves = move_bodies(&mut chunks, context.materials, context.gravity);
+
         // Prepare chunks for calculation
         self.prepare_chunks(chunks.values_mut().collect());
 
         // Update chunks, while there are any updates queued
+        let mut update_cycles = 0;
         while !self.update_queue.is_empty() {
+            update_cycles += 1;
+            assert!(
+                update_cycles <= MAX_UPDATE_CYCLES,
+                "chunks {:?} are stuck waiting for each other",
+                self.update_queue
+            );
+
             // Get chunks to update
-            let update_queue = chunks
+            let mut update_queue = chunks
                 .iter_mut()
                 .filter_map(|(chunk_pos, chunk)| {
                     if self.update_queue.remove(chunk_pos) {
@@ -74,22 +97,42 @@ impl Calculator {
                 })
                 .collect::<Vec<_>>();
 
+            // Chunks are always updated in the same order, so that the results are reproducible
+            update_queue.sort_by_key(|(chunk, ..)| (chunk.chunk_pos.x, chunk.chunk_pos.y));
+
             // Update chunks
-            self.update_chunks(update_queue.into_par_iter());
+            self.update_chunks(update_queue.into_par_iter(), context);
         }
 
         // Perform movement and collect view updates
         let mut view_update = HashMap::with_capacity(self.calculations.len());
+        let mut extra_updates = Vec::new();
         for (chunk_pos, chunk) in &mut chunks {
             let (calculation, _) = self.calculations.remove(chunk_pos).unwrap();
-            chunk.movement(calculation.moves_to);
+            extra_updates.extend(chunk.movement(calculation.moves_to, calculation.swaps));
             view_update.insert(*chunk_pos, calculation.view_update);
         }
 
+        // Show tiles moved with rigid bodies, unless the calculation has changed them since
+        for tile in body_moves {
+            if let Some(view_update) = view_update.get_mut(&tile.chunk_pos) {
+                if view_update[tile.index].is_none() {
+                    view_update[tile.index] = Some(chunks[&tile.chunk_pos].tile(tile.index));
+                }
+            }
+        }
+
+        // Queue updates for the next frame
+        for update_tile in extra_updates {
+            if let Some(chunk) = chunks.get_mut(&update_tile.chunk_pos) {
+                chunk.queue_update(update_tile.index);
+            }
+        }
+
         view_update
     }
 
-    fn prepare_chunks<'a>(&mut self, update_queue: Vec<&mut &'a mut Chunk>) {
+    fn prepare_chunks(&mut self, update_queue: Vec<&mut &mut Chunk>) {
         // Prepare chunks for calculation
         let calculations = Mutex::new(&mut self.calculations);
         update_queue
@@ -102,36 +145,58 @@ impl Calculator {
 
     fn update_chunks<'a: 'b, 'b>(
         &mut self,
-        update_chunks: impl ParallelIterator<Item = ChunkInformation<'a, 'b>>,
+        update_chunks: impl IndexedParallelIterator<Item = ChunkInformation<'a, 'b>>,
+        context: TickContext,
     ) {
         // Update chunks in parallel
-        let calculator = Mutex::new(self);
-        update_chunks.for_each(
-            |(chunk, mut calculation, mut dependencies, updates, cross_moves)| {
-                // Calculation cycle is independent from other chunks
-                let (chunk_updates, extra_updates, cross_moves) = chunk.calculation_cycle(
-                    &mut calculation,
-                    &mut dependencies,
-                    updates,
-                    cross_moves,
-                );
-
-                // Update information about chunk
-                let mut calculator = calculator.lock().unwrap();
-                calculator.update_information(
-                    chunk.chunk_pos,
-                    chunk_updates,
-                    extra_updates,
-                    cross_moves,
-                    &mut dependencies,
-                    &calculation.dependencies,
-                );
-
-                calculator
-                    .calculations
-                    .insert(chunk.chunk_pos, (calculation, dependencies));
-            },
-        );
+        let results = update_chunks
+            .map(
+                |(chunk, mut calculation, mut dependencies, updates, cross_moves)| {
+                    // Calculation cycle is independent from other chunks
+                    let (chunk_updates, extra_updates, cross_moves) = chunk.calculation_cycle(
+                        &mut calculation,
+                        &mut dependencies,
+                        updates,
+                        cross_moves,
+                        context,
+                    );
+                    (
+                        chunk.chunk_pos,
+                        calculation,
+                        dependencies,
+                        chunk_updates,
+                        extra_updates,
+                        cross_moves,
+                    )
+                },
+            )
+            .collect::<Vec<_>>();
+
+        // Return all calculations first, so that dependencies
+        // between the updated chunks are seen by each other
+        let mut updates = Vec::with_capacity(results.len());
+        for (chunk_pos, calculation, dependencies, chunk_updates, extra_updates, cross_moves) in
+            results
+        {
+            self.calculations
+                .insert(chunk_pos, (calculation, dependencies));
+            updates.push((chunk_pos, chunk_updates, extra_updates, cross_moves));
+        }
+
+        // Update information about chunks
+        for (chunk_pos, chunk_updates, extra_updates, cross_moves) in updates {
+            let (calculation, mut dependencies) = self.calculations.remove(&chunk_pos).unwrap();
+            self.update_information(
+                chunk_pos,
+                chunk_updates,
+                extra_updates,
+                cross_moves,
+                &mut dependencies,
+                &calculation,
+            );
+            self.calculations
+                .insert(chunk_pos, (calculation, dependencies));
+        }
     }
 
     fn update_information(
@@ -139,10 +204,18 @@ impl Calculator {
         chunk_pos: IVec2,
         chunk_updates: DataArray<Option<MoveInfo>>,
         extra_updates: Vec<Tile>,
-        cross_moves: HashMap<Tile, TileInfo>,
+        cross_moves: HashMap<Tile, CrossMove>,
         dependencies: &mut Dependencies,
-        tile_dependencies: &DataArray<Option<Tile>>,
+        calculation: &ChunkCalculation,
     ) {
+        let tile_dependencies = &calculation.dependencies;
+
+        // Forget unknown dependencies that no tile waits for anymore
+        let waiting = tile_dependencies.iter().flatten().collect::<HashSet<_>>();
+        dependencies.retain(|tile, move_info| {
+            !matches!(move_info, MoveInfo::Unknown) || waiting.contains(tile)
+        });
+
         // Queue updates for other chunks
         for update_tile in
             extra_updates
@@ -163,10 +236,12 @@ impl Calculator {
             }
         }
 
-        // Register cross moves
-        for (cross_tile, cross_tile_info) in cross_moves {
+        // Register cross moves in a fixed order
+        let mut cross_moves = cross_moves.into_iter().collect::<Vec<_>>();
+        cross_moves.sort_by_key(|(tile, _)| (tile.chunk_pos.x, tile.chunk_pos.y, tile.index));
+        for (cross_tile, cross_move) in cross_moves {
             if let Some(cross_moves) = self.cross_moves.get_mut(&cross_tile.chunk_pos) {
-                cross_moves[cross_tile.index] = Some(cross_tile_info);
+                cross_moves.push((cross_tile.index, cross_move));
                 // Queue chunk update
                 self.update_queue.insert(cross_tile.chunk_pos);
             }
@@ -199,19 +274,16 @@ impl Calculator {
             if let MoveInfo::Unknown = move_info {
                 // Look for evaluated chunks
                 *move_info = match self.chunk_calculations.get(&depend_tile.chunk_pos) {
-                    Some(calculation) => {
-                        // If dependent tile depends on current tile, then movement is not allowed
-                        match self.calculations.get(&depend_tile.chunk_pos) {
-                            Some((calculation, _))
-                                if calculation.dependencies[depend_tile.index]
-                                    == Some(Tile {
-                                        chunk_pos,
-                                        index: tile,
-                                    }) =>
-                            {
-                                MoveInfo::Recursive
-                            }
-                            _ => calculation[depend_tile.index],
+                    Some(tiles) => {
+                        // If dependent tile waits for current tile, then movement is not allowed
+                        let tile = Tile {
+                            chunk_pos,
+                            index: tile,
+                        };
+                        if self.waits_for(*depend_tile, tile, calculation) {
+                            MoveInfo::Recursive
+                        } else {
+                            tiles[depend_tile.index]
                         }
                     }
                     None => MoveInfo::Impossible,
@@ -226,17 +298,252 @@ impl Calculator {
         }
     }
 
+    /// Whether a tile waits for another or for itself, following the tiles they wait for
+    /// through all chunks, `current` is the calculation of the chunk that's being updated
+    fn waits_for(&self, start: Tile, other: Tile, current: &ChunkCalculation) -> bool {
+        let mut tile = start;
+        let mut visited = HashSet::new();
+        while visited.insert(tile) {
+            let calculation = match self.calculations.get(&tile.chunk_pos) {
+                Some((calculation, _)) => calculation,
+                None if tile.chunk_pos == other.chunk_pos => current,
+                None => return false,
+            };
+            match calculation.waits_on[tile.index] {
+                // The cycle may close through another tile of the chunk than `other`,
+                // the chain then leads back to where it started
+                Some(next) if next == other || next == start => return true,
+                Some(next) => tile = next,
+                None => return false,
+            }
+        }
+        false
+    }
+
     fn take_updates_moves(
         &mut self,
         chunk_pos: &IVec2,
-    ) -> (Option<DataArray<bool>>, Option<DataArray<Option<TileInfo>>>) {
+    ) -> (Option<DataArray<bool>>, Option<CrossMoves>) {
         (
             self.extra_updates
-                .get_mut(&chunk_pos)
+                .get_mut(chunk_pos)
                 .map(|extra_updates| std::mem::replace(extra_updates, data_array(false))),
-            self.cross_moves
-                .get_mut(&chunk_pos)
-                .map(|cross_moves| std::mem::replace(cross_moves, default_data_array())),
+            self.cross_moves.get_mut(chunk_pos).map(std::mem::take),
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use macroquad::prelude::{ivec2, IVec2};
+
+    use super::*;
+    use crate::game::{
+        gravity::{Gravity, GravityField},
+        layers::LayerValues,
+        material::MaterialRegistry,
+        World,
+    };
+
+    const MATERIALS: &str = r#"
+        [[material]]
+        name = "barrier"
+        color = [255, 255, 255]
+        movement = "static"
+
+        [[material]]
+        name = "sand"
+        color = [253, 249, 0]
+        movement = "powder"
+        density = 1.6
+
+        [[material]]
+        name = "water"
+        color = [0, 121, 241]
+        movement = "liquid"
+        density = 1.0
+
+        [[material]]
+        name = "smoke"
+        color = [90, 90, 90]
+        movement = "gas"
+        density = 0.1
+
+        [[material]]
+        name = "ash"
+        color = [60, 60, 60]
+        movement = "powder"
+        density = 0.5
+        lifetime = 1000
+    "#;
+
+    fn scene() -> World {
+        scene_with(ivec2(0, -1))
+    }
+
+    fn scene_with(gravity: IVec2) -> World {
+        let materials = MaterialRegistry::from_toml(MATERIALS).unwrap();
+        let gravity = GravityField::new(Gravity::new(gravity).unwrap());
+        World::new(materials, gravity, 0)
+    }
+
+    fn layers(world: &World, position: IVec2) -> LayerValues {
+        let tile = Tile::from_global_position(position);
+        world.chunks[&tile.chunk_pos].layers.get(tile.index)
+    }
+
+    fn set_layers(world: &mut World, position: IVec2, values: LayerValues) {
+        let tile = Tile::from_global_position(position);
+        let chunk = world.chunks.get_mut(&tile.chunk_pos).unwrap();
+        chunk.layers.set(tile.index, values);
+    }
+
+    #[test]
+    fn resolves_dependency_cycles_through_a_chunk() {
+        let mut world = scene();
+        // The sand falls into the water, which can only flow into the smoke,
+        // which can only rise into the sand, the sand is in the chunk above the others
+        world.place("sand", ivec2(8, 50));
+        world.place("water", ivec2(8, 49));
+        world.place("smoke", ivec2(9, 49));
+        for &(x, y) in &[
+            (7, 51),
+            (9, 51),
+            (7, 50),
+            (9, 50),
+            (10, 50),
+            (7, 49),
+            (10, 49),
+        ] {
+            world.place("barrier", ivec2(x, y));
+        }
+        for x in 7..=10 {
+            world.place("barrier", ivec2(x, 48));
+        }
+
+        world.perform_tick();
+
+        assert_eq!(world.find("sand").len(), 1);
+        assert_eq!(world.find("water").len(), 1);
+        assert_eq!(world.find("smoke").len(), 1);
+    }
+
+    #[test]
+    fn returns_tiles_that_lose_a_cross_chunk_move() {
+        let mut world = scene();
+        // Sand and water from two chunks move into the same free tile of a third one
+        world.place("sand", ivec2(49, 50));
+        world.place("water", ivec2(50, 50));
+        world.place("barrier", ivec2(51, 50));
+        for &(x, y) in &[(48, 48), (49, 48), (48, 49), (50, 49), (51, 49)] {
+            world.place("barrier", ivec2(x, y));
+        }
+
+        // The water is returned to where it came from
+        world.perform_tick();
+        assert_eq!(world.find("sand"), vec![ivec2(49, 49)]);
+        assert_eq!(world.find("water"), vec![ivec2(50, 50)]);
+    }
+
+    #[test]
+    fn layers_move_with_tiles_across_chunks() {
+        let mut world = scene();
+        // The sand sinks through the water in the chunk below, so both tiles change chunks
+        world.place("sand", ivec2(8, 50));
+        world.place("water", ivec2(8, 49));
+        for &(x, y) in &[
+            (7, 50),
+            (9, 50),
+            (7, 49),
+            (9, 49),
+            (7, 48),
+            (8, 48),
+            (9, 48),
+        ] {
+            world.place("barrier", ivec2(x, y));
+        }
+        let sand = LayerValues {
+            temperature: 300.0,
+            age: 7,
+            shade: 3,
+            ..LayerValues::default()
+        };
+        let water = LayerValues {
+            temperature: 5.0,
+            age: 2,
+            shade: 9,
+            ..LayerValues::default()
+        };
+        set_layers(&mut world, ivec2(8, 50), sand);
+        set_layers(&mut world, ivec2(8, 49), water);
+
+        world.perform_tick();
+        assert_eq!(world.find("sand"), vec![ivec2(8, 49)]);
+        assert_eq!(world.find("water"), vec![ivec2(8, 50)]);
+        for (moved, expected) in &[
+            (layers(&world, ivec2(8, 49)), sand),
+            (layers(&world, ivec2(8, 50)), water),
+        ] {
+            assert_eq!(moved.temperature, expected.temperature);
+            assert_eq!(moved.age, expected.age);
+            assert_eq!(moved.shade, expected.shade);
+        }
+    }
+
+    #[test]
+    fn active_tiles_move_once_a_neighbour_has_moved() {
+        let mut world = scene();
+        // The decaying ash stays active while it rests on the water,
+        // which flows away once the wall next to it is removed
+        for x in 0..50 {
+            world.place("barrier", ivec2(x, 0));
+        }
+        world.place("barrier", ivec2(9, 1));
+        world.place("water", ivec2(10, 1));
+        world.place("water", ivec2(11, 1));
+        world.place("barrier", ivec2(12, 1));
+        world.place("ash", ivec2(10, 2));
+        world.place("barrier", ivec2(11, 2));
+        world.perform_tick();
+
+        world.set_tile(Tile::from_global_position(ivec2(12, 1)), None);
+        for _ in 0..10 {
+            world.perform_tick();
+        }
+        assert_eq!(world.find("ash")[0].y, 1);
+    }
+
+    #[test]
+    fn only_falling_tiles_speed_up() {
+        let mut world = scene();
+        world.place("sand", ivec2(10, 40));
+        world.place("smoke", ivec2(20, 10));
+
+        for _ in 0..3 {
+            world.perform_tick();
+        }
+        let velocity = |position: IVec2| {
+            let tile = Tile::from_global_position(position);
+            world.chunks[&tile.chunk_pos].layers.velocity[tile.index]
+        };
+        assert!(velocity(world.find("sand")[0]) > 0.0);
+        assert_eq!(velocity(world.find("smoke")[0]), 0.0);
+    }
+
+    #[test]
+    fn swaps_across_a_chunk_border_settle_under_any_gravity() {
+        for &gravity in &[ivec2(0, -1), ivec2(0, 1), ivec2(1, 0), ivec2(1, 1)] {
+            // Under upward gravity the sand sinks into the smoke across the border
+            // between chunks (-1, 1) and (-1, 2), tiles on both sides of it swap diagonally
+            let mut world = scene_with(gravity);
+            world.fill("sand", ivec2(-30, 90), ivec2(-10, 99));
+            world.fill("smoke", ivec2(-45, 100), ivec2(-20, 120));
+
+            for _ in 0..20 {
+                world.tick();
+            }
+            assert_eq!(world.find("sand").len(), 21 * 10);
+            assert_eq!(world.find("smoke").len(), 26 * 21);
+        }
+    }
+}
@@ -2,12 +2,25 @@ use std::collections::HashMap;
 
 use macroquad::prelude::{ivec2, uvec2, IVec2, UVec2};
 
-use crate::constants::{CHUNK_SIZE, CHUNK_SIZE_X, CHUNK_SIZE_Y};
+use crate::constants::{
+    CHARGE_TICKS, CHUNK_SIZE, CHUNK_SIZE_X, CHUNK_SIZE_Y, GRAVITY, HEAT_FLOW_RATE,
+    TERMINAL_VELOCITY,
+};
 
-use super::tile::{Tile, TileInfo};
+use super::{
+    layers::{LayerValues, TileLayers},
+    material::{MaterialId, MaterialRegistry, MovementRule},
+    random::{RandomStream, TileRandom},
+    tick::TickContext,
+    tile::{BodyId, Tile, TileInfo},
+    tile_move::{HorizontalMove, TileMove},
+    tile_move_direction::TileMoveDirection,
+};
 
 pub type Dependencies = HashMap<Tile, MoveInfo>;
 
+pub type CrossMoves = Vec<(usize, CrossMove)>;
+
 pub type DataArray<T> = Vec<T>;
 
 pub fn data_array<T: Copy>(default_value: T) -> DataArray<T> {
@@ -38,16 +51,20 @@ pub fn tile_position_to_index(tile_position: UVec2) -> usize {
         "position {} out of chunk bounds",
         tile_position
     );
-    let index = tile_position.x as usize + tile_position.y as usize * CHUNK_SIZE_X;
-    index
+    tile_position.x as usize + tile_position.y as usize * CHUNK_SIZE_X
+}
+
+/// Shifts to the tiles in a square around a tile, including the tile itself
+fn square(distance: i32) -> impl Iterator<Item = IVec2> {
+    (-distance..=distance).flat_map(move |dx| (-distance..=distance).map(move |dy| ivec2(dx, dy)))
 }
 
 pub struct Chunk {
     pub chunk_pos: IVec2,
     pub tiles: DataArray<bool>,
     pub tile_info: DataArray<Option<TileInfo>>,
-    pub need_update: DataArray<bool>,
-    cant_move: DataArray<bool>,
+    /// Per-tile data, partly moving together with the tiles
+    pub layers: TileLayers,
 }
 
 impl Chunk {
@@ -56,8 +73,7 @@ impl Chunk {
             chunk_pos,
             tiles: data_array(false),
             tile_info: default_data_array(),
-            need_update: data_array(false),
-            cant_move: data_array(false),
+            layers: TileLayers::default(),
         }
     }
 
@@ -65,17 +81,65 @@ impl Chunk {
         self.tile_info.iter().enumerate()
     }
 
-    pub fn set_tile(&mut self, index: usize, tile_info: Option<TileInfo>) -> Vec<Tile> {
-        self.need_update[index] = tile_info.is_some();
-        self.tiles[index] = tile_info.is_some();
-        self.tile_info[index] = tile_info;
-        self.cant_move[index] = false;
+    pub fn set_tile(&mut self, index: usize, tile: Option<MovingTile>) -> Vec<Tile> {
+        self.layers.need_update[index] = tile.is_some();
+        self.tiles[index] = tile.is_some();
+        match tile {
+            Some(tile) => {
+                self.tile_info[index] = Some(tile.tile_info);
+                self.layers.set(index, tile.layers);
+            }
+            None => {
+                self.tile_info[index] = None;
+                self.layers.set(index, LayerValues::default());
+            }
+        }
+        self.layers.cant_move[index] = false;
+        self.queue_updates_around(index, 1)
+    }
+
+    /// Copies the tile together with the data that moves with it
+    pub fn tile(&self, index: usize) -> Option<MovingTile> {
+        Some(MovingTile {
+            tile_info: self.tile_info[index].clone()?,
+            layers: self.layers.get(index),
+        })
+    }
+
+    /// Changes the material of a tile, keeping its temperature and state,
+    /// a tile that appears in a free place is spawned with `random`
+    pub fn replace_tile(
+        &mut self,
+        index: usize,
+        material: Option<MaterialId>,
+        materials: &MaterialRegistry,
+        random: TileRandom,
+    ) -> Vec<Tile> {
+        match (material, &mut self.tile_info[index]) {
+            (Some(material), Some(tile_info)) => {
+                tile_info.material = material;
+                self.layers.saturation[index] = 0;
+                self.layers.age[index] = 0;
+            }
+            (Some(material), tile_info) => {
+                let spawned = MovingTile::spawn(material, materials, random);
+                *tile_info = Some(spawned.tile_info);
+                self.layers.set(index, spawned.layers);
+            }
+            (None, tile_info) => {
+                *tile_info = None;
+                self.layers.set(index, LayerValues::default());
+            }
+        }
+        self.tiles[index] = self.tile_info[index].is_some();
+        self.layers.need_update[index] = self.tiles[index];
+        self.layers.cant_move[index] = false;
         self.queue_updates_around(index, 1)
     }
 
     pub fn queue_update(&mut self, index: usize) {
-        self.need_update[index] = true;
-        self.cant_move[index] = false;
+        self.layers.need_update[index] = true;
+        self.layers.cant_move[index] = false;
     }
 
     pub fn prepare_calculation(&mut self) -> (ChunkCalculation, Dependencies) {
@@ -86,20 +150,24 @@ impl Chunk {
             moves_to: default_data_array(),
             update_tiles: {
                 let mut update_tiles = Vec::new();
-                for index in 0..self.need_update.len() {
-                    if self.need_update[index] {
+                for index in 0..self.layers.need_update.len() {
+                    if self.layers.need_update[index] {
                         if self.tiles[index] {
                             update_tiles.push(index);
                         } else {
-                            self.need_update[index] = false;
+                            self.layers.need_update[index] = false;
                         }
                     }
                 }
                 update_tiles
             },
             unknown: data_array(false),
+            awaiting_reply: data_array(false),
+            swaps: Vec::new(),
             dependencies: default_data_array(),
+            waits_on: default_data_array(),
             view_update: default_data_array(),
+            emitted: false,
         };
 
         (calculation, HashMap::new())
@@ -110,8 +178,13 @@ impl Chunk {
         calculation: &mut ChunkCalculation,
         dependencies: &mut Dependencies,
         updates: Option<DataArray<bool>>,
-        cross_moves: Option<DataArray<Option<TileInfo>>>,
-    ) -> (Vec<Option<MoveInfo>>, Vec<Tile>, HashMap<Tile, TileInfo>) {
+        cross_moves: Option<CrossMoves>,
+        context: TickContext,
+    ) -> (Vec<Option<MoveInfo>>, Vec<Tile>, HashMap<Tile, CrossMove>) {
+        let mut chunk_updates = data_array(None);
+        let mut extra_updates = Vec::new();
+        let mut new_cross_moves = HashMap::new();
+
         // Register extra updates
         if let Some(updates) = updates {
             for update_index in updates
@@ -121,23 +194,80 @@ impl Chunk {
             {
                 calculation.update_tiles.push(update_index);
                 calculation.checked[update_index] = false;
-                self.cant_move[update_index] = false;
+                self.layers.cant_move[update_index] = false;
             }
         }
 
         // Register cross-chunk moves
         if let Some(cross_moves) = cross_moves {
-            for (move_to, tile_info) in cross_moves
-                .into_iter()
-                .enumerate()
-                .filter_map(|(index, tile_info)| tile_info.map(|tile_info| (index, tile_info)))
-            {
-                calculation.view_update[move_to] = Some(Some(tile_info.clone()));
-                calculation.moves_to[move_to] = Some(tile_info);
-                self.need_update[move_to] = true;
+            for (move_to, cross_move) in cross_moves {
+                let tile = match cross_move {
+                    CrossMove::Move { tile, origin, swap } => {
+                        // Drains delete incoming tiles
+                        if self.is_drain(move_to, context.materials) {
+                            new_cross_moves.insert(origin, CrossMove::Placed);
+                            continue;
+                        }
+
+                        let displaced = if swap
+                            && calculation.moves_to[move_to].is_none()
+                            && !calculation.awaiting_reply[move_to]
+                        {
+                            self.take_tile(move_to)
+                        } else {
+                            None
+                        };
+                        match displaced {
+                            Some(displaced) => {
+                                // Send the displaced tile back to where the incoming one came from
+                                calculation.moves_from[move_to] = true;
+                                self.layers.cant_move[move_to] = false;
+                                self.update_tiles_around(
+                                    move_to,
+                                    1,
+                                    calculation,
+                                    &mut extra_updates,
+                                );
+                                calculation.swaps.push(move_to);
+                                new_cross_moves.insert(origin, CrossMove::Returned(displaced));
+                            }
+                            None if self.is_free(move_to, calculation) => {
+                                new_cross_moves.insert(origin, CrossMove::Placed);
+                            }
+                            None => {
+                                // Another tile has taken the place, so return the incoming tile
+                                new_cross_moves.insert(origin, CrossMove::Returned(tile));
+                                continue;
+                            }
+                        }
+                        tile
+                    }
+                    // Spawned tiles only land on free tiles
+                    CrossMove::Spawn(tile) if self.is_free(move_to, calculation) => tile,
+                    CrossMove::Spawn(_) => continue,
+                    CrossMove::Placed => {
+                        calculation.awaiting_reply[move_to] = false;
+                        continue;
+                    }
+                    // The origin has been kept free for the returned tile
+                    CrossMove::Returned(tile) => {
+                        calculation.awaiting_reply[move_to] = false;
+                        tile
+                    }
+                };
+
+                calculation.view_update[move_to] = Some(Some(tile.clone()));
+                calculation.moves_to[move_to] = Some(tile);
+                self.layers.need_update[move_to] = true;
             }
         }
 
+        // Spawn tiles from emitters once per tick
+        if !calculation.emitted {
+            calculation.emitted = true;
+            self.emit_tiles(calculation, &mut new_cross_moves, context);
+        }
+
         // Clear unknowns
         for unknown_tile in
             calculation
@@ -155,26 +285,31 @@ impl Chunk {
         {
             calculation.update_tiles.push(unknown_tile);
             calculation.checked[unknown_tile] = false;
-            self.cant_move[unknown_tile] = false;
+            calculation.waits_on[unknown_tile] = None;
+            self.layers.cant_move[unknown_tile] = false;
         }
 
         // Calculate tiles
-        let mut chunk_updates = data_array(None);
-        let mut extra_updates = Vec::new();
-        let mut cross_moves = HashMap::new();
         while !calculation.update_tiles.is_empty() {
             let update_index = calculation.update_tiles.remove(0);
-            let move_info = self.calculate_tile(
+            let move_info = match self.calculate_tile(
                 update_index,
                 calculation,
                 &mut extra_updates,
-                &mut cross_moves,
+                &mut new_cross_moves,
                 dependencies,
-            );
+                context,
+            ) {
+                // Let other chunks know whether they can swap with this tile
+                MoveInfo::Impossible => {
+                    self.displacement_info(update_index, calculation, context.materials)
+                }
+                move_info => move_info,
+            };
             chunk_updates[update_index] = Some(move_info);
         }
 
-        (chunk_updates, extra_updates, cross_moves)
+        (chunk_updates, extra_updates, new_cross_moves)
     }
 
     fn calculate_tile(
@@ -182,16 +317,30 @@ impl Chunk {
         update_index: usize,
         calculation: &mut ChunkCalculation,
         extra_updates: &mut Vec<Tile>,
-        cross_moves: &mut HashMap<Tile, TileInfo>,
+        cross_moves: &mut HashMap<Tile, CrossMove>,
         dependencies: &mut Dependencies,
+        context: TickContext,
     ) -> MoveInfo {
+        let materials = context.materials;
+        // Drains take in any tile
+        if self.is_drain(update_index, materials) {
+            return MoveInfo::Possible;
+        }
+
         // If this tile couldn't move last frame
         // or another tile is going to move here,
         // then movement is not allowed
-        if self.cant_move[update_index] || calculation.moves_to[update_index].is_some() {
+        if self.layers.cant_move[update_index] || calculation.moves_to[update_index].is_some() {
             return MoveInfo::Impossible;
         }
 
+        // If the tile has moved into another chunk, which hasn't replied yet,
+        // then it's unknown whether it will be placed there or return
+        if calculation.awaiting_reply[update_index] {
+            calculation.unknown[update_index] = true;
+            return MoveInfo::Unknown;
+        }
+
         // If there is no tile
         // or we've calculated that this tile can move,
         // then movement is allowed
@@ -210,56 +359,130 @@ impl Chunk {
         let checked = calculation.checked[update_index];
         calculation.checked[update_index] = true;
         if checked {
-            self.cant_move[update_index] = true;
+            self.layers.cant_move[update_index] = true;
             return MoveInfo::Impossible;
         }
 
-        // Check for possible moves
-        for direction in self.tile_info[update_index]
-            .as_ref()
-            .unwrap()
-            .movement_directions()
+        // Check for possible moves, the first direction is the one tiles fall in,
+        // directions are described for gravity pointing down
+        let position = Tile {
+            chunk_pos: self.chunk_pos,
+            index: update_index,
+        }
+        .global_position();
+        let gravity = context.gravity.at(position);
+        let mut random = context.random.tile(position, RandomStream::Movement);
+        let mirrored = random.next_bool();
+        let tile = self.tile(update_index).unwrap();
+        let tile_info = &tile.tile_info;
+        let slides = tile_info.slides(
+            self.layers.velocity[update_index] > 0.0,
+            materials,
+            random.next_f32(),
+        );
+
+        // The sideways push of the wind is the chance of drifting with it
+        let push = self.layers.wind[update_index].dot(gravity.right())
+            * materials.get(tile_info.material).drag;
+        let drift = if push != 0.0 && random.next_f32() < push.abs() {
+            Some(if push > 0.0 {
+                HorizontalMove::Right
+            } else {
+                HorizontalMove::Left
+            })
+        } else {
+            None
+        };
+
+        for (priority, direction) in tile
+            .movement_directions(materials, mirrored, drift)
+            .into_iter()
+            .enumerate()
+            .filter(|(_, direction)| slides || !direction.is_diagonal())
         {
+            // Drifting tiles fall towards the wind first, then straight,
+            // rising tiles don't speed up
+            let falling =
+                (priority == 0 || drift.is_some() && priority == 1) && direction.direction().y < 0;
             // Check if target is inside the current chunk
-            match self.shift_position(update_index, direction.direction()) {
+            match self.shift_position(update_index, gravity.rotate(direction.direction())) {
                 Ok(target_index) => {
                     // Inside the current chunk -> check if movement is possible
-                    match self.calculate_tile(
+                    let move_info = self.calculate_tile(
                         target_index,
                         calculation,
                         extra_updates,
                         cross_moves,
                         dependencies,
-                    ) {
+                        context,
+                    );
+
+                    // This tile has been displaced while calculating the target
+                    if self.tile_info[update_index].is_none() {
+                        return MoveInfo::Impossible;
+                    }
+
+                    match move_info {
                         MoveInfo::Unknown => {
                             calculation.unknown[update_index] = true;
+                            calculation.waits_on[update_index] = calculation.waits_on[target_index];
                             return MoveInfo::Unknown;
                         }
-                        MoveInfo::Impossible => {}
+                        MoveInfo::Impossible => {
+                            // Try to sink through the target
+                            let target_material = match &self.tile_info[target_index] {
+                                Some(target_info)
+                                    if calculation.moves_to[target_index].is_none() =>
+                                {
+                                    Some(target_info.material)
+                                }
+                                _ => None,
+                            };
+                            if let Some(target_material) = target_material {
+                                if self.can_displace(
+                                    update_index,
+                                    target_material,
+                                    &direction,
+                                    materials,
+                                ) {
+                                    self.swap_tiles(
+                                        update_index,
+                                        target_index,
+                                        direction,
+                                        calculation,
+                                        extra_updates,
+                                    );
+                                    return MoveInfo::Impossible;
+                                }
+                            }
+                        }
                         MoveInfo::Recursive => unreachable!(),
+                        MoveInfo::Displaceable(_) => unreachable!(),
                         MoveInfo::Possible => {
                             // Remove dependency
                             calculation.dependencies[update_index] = None;
 
                             // Register the move
-                            let mut tile_info =
-                                std::mem::take(self.tile_info.get_mut(update_index).unwrap())
-                                    .unwrap();
-                            tile_info.register_move(direction);
+                            let mut tile = self.take_tile(update_index).unwrap();
+                            tile.register_move(direction, falling);
                             calculation.moves[update_index] = Some(target_index);
                             calculation.moves_from[update_index] = true;
-                            calculation.moves_to[target_index] = Some(tile_info.clone());
-                            self.cant_move[update_index] = false;
+                            self.layers.cant_move[update_index] = false;
+
+                            // Drains delete the tile, otherwise it's placed at the target
+                            if !self.is_drain(target_index, materials) {
+                                calculation.view_update[target_index] = Some(Some(tile.clone()));
+                                calculation.moves_to[target_index] = Some(tile);
+
+                                // Queue update for the next frame
+                                self.layers.need_update[target_index] = true;
+                            }
 
                             // Update view
-                            calculation.view_update[target_index] = Some(Some(tile_info));
                             if calculation.moves_to[update_index].is_none() {
                                 calculation.view_update[update_index] = Some(None);
                             }
 
-                            // Queue update for the next frame
-                            self.need_update[target_index] = true;
-
                             // Update nearby lazy tiles
                             self.update_tiles_around(update_index, 1, calculation, extra_updates);
                             return MoveInfo::Possible;
@@ -272,11 +495,7 @@ impl Chunk {
                     let is_current_dependency =
                         calculation.dependencies[update_index] == Some(tile);
                     // If other tile depends on it, then movement is not allowed
-                    let vacant = if let std::collections::hash_map::Entry::Vacant(_) = &entry {
-                        true
-                    } else {
-                        false
-                    };
+                    let vacant = matches!(&entry, std::collections::hash_map::Entry::Vacant(_));
 
                     if is_current_dependency || vacant {
                         if !is_current_dependency {
@@ -288,23 +507,72 @@ impl Chunk {
                         match dependency {
                             MoveInfo::Unknown => {
                                 calculation.unknown[update_index] = true;
+                                calculation.waits_on[update_index] = Some(tile);
                                 return MoveInfo::Unknown;
                             }
                             MoveInfo::Impossible => {}
                             MoveInfo::Recursive => {
-                                // Reset dependency
-                                if is_current_dependency {
-                                    *dependency = MoveInfo::Unknown;
+                                // The tiles want to move into each other,
+                                // resetting the dependency would make them
+                                // wait for each other forever
+                            }
+                            MoveInfo::Displaceable(target_material) => {
+                                let target_material = *target_material;
+                                if self.can_displace(
+                                    update_index,
+                                    target_material,
+                                    &direction,
+                                    materials,
+                                ) {
+                                    // Register the swap, the displaced tile
+                                    // will be sent back by the other chunk
+                                    let mut moving_tile = self.take_tile(update_index).unwrap();
+                                    moving_tile.register_move(direction, false);
+                                    cross_moves.insert(
+                                        tile,
+                                        CrossMove::Move {
+                                            tile: moving_tile,
+                                            origin: Tile {
+                                                chunk_pos: self.chunk_pos,
+                                                index: update_index,
+                                            },
+                                            swap: true,
+                                        },
+                                    );
+                                    calculation.moves_from[update_index] = true;
+                                    calculation.awaiting_reply[update_index] = true;
+                                    calculation.unknown[update_index] = true;
+                                    calculation.view_update[update_index] = Some(None);
+                                    self.layers.cant_move[update_index] = false;
+
+                                    // Update nearby lazy tiles
+                                    self.update_tiles_around(
+                                        update_index,
+                                        1,
+                                        calculation,
+                                        extra_updates,
+                                    );
+                                    return MoveInfo::Unknown;
                                 }
                             }
                             MoveInfo::Possible => {
                                 // Register the move
-                                let mut tile_info =
-                                    std::mem::take(self.tile_info.get_mut(update_index).unwrap())
-                                        .unwrap();
-                                tile_info.register_move(direction);
-                                cross_moves.insert(tile, tile_info);
+                                let mut moving_tile = self.take_tile(update_index).unwrap();
+                                moving_tile.register_move(direction, falling);
+                                cross_moves.insert(
+                                    tile,
+                                    CrossMove::Move {
+                                        tile: moving_tile,
+                                        origin: Tile {
+                                            chunk_pos: self.chunk_pos,
+                                            index: update_index,
+                                        },
+                                        swap: false,
+                                    },
+                                );
                                 calculation.moves_from[update_index] = true;
+                                calculation.awaiting_reply[update_index] = true;
+                                calculation.unknown[update_index] = true;
 
                                 // Update view
                                 if calculation.moves_to[update_index].is_none() {
@@ -318,7 +586,8 @@ impl Chunk {
                                     calculation,
                                     extra_updates,
                                 );
-                                return MoveInfo::Possible;
+                                // Tiles can follow once the other chunk has placed it
+                                return MoveInfo::Unknown;
                             }
                         }
                     }
@@ -327,28 +596,163 @@ impl Chunk {
         }
 
         // There are no possible moves
-        // Set this tile into lazy mode
-        self.cant_move[update_index] = true;
-        self.need_update[update_index] = false;
+        // Set this tile into lazy mode, tiles that change without moving stay updated
+        self.layers.spreading[update_index] = false;
+        self.layers.velocity[update_index] = 0.0;
+        self.layers.cant_move[update_index] = true;
+        self.layers.need_update[update_index] =
+            self.tile(update_index).unwrap().stays_active(materials);
         MoveInfo::Impossible
     }
 
+    fn is_drain(&self, index: usize, materials: &MaterialRegistry) -> bool {
+        self.tile_info[index]
+            .as_ref()
+            .is_some_and(|tile_info| tile_info.is_drain(materials))
+    }
+
+    /// Spawns tiles from active emitters into free tiles next to them
+    fn emit_tiles(
+        &mut self,
+        calculation: &mut ChunkCalculation,
+        cross_moves: &mut HashMap<Tile, CrossMove>,
+        context: TickContext,
+    ) {
+        let materials = context.materials;
+        for index in calculation.update_tiles.clone() {
+            let emitter = match &self.tile_info[index] {
+                Some(tile_info) => match materials.get(tile_info.material).emitter {
+                    Some(emitter) => emitter,
+                    None => continue,
+                },
+                None => continue,
+            };
+            let position = Tile {
+                chunk_pos: self.chunk_pos,
+                index,
+            }
+            .global_position();
+            let mut random = context.random.tile(position, RandomStream::Emission);
+            if random.next_f32() >= emitter.rate {
+                continue;
+            }
+
+            let tile = MovingTile::spawn(emitter.material, materials, random);
+            let direction = context.gravity.at(position).rotate(emitter.direction);
+            match self.shift_position(index, direction) {
+                Ok(target_index) => {
+                    if self.tile_info[target_index].is_none()
+                        && calculation.moves_to[target_index].is_none()
+                    {
+                        calculation.view_update[target_index] = Some(Some(tile.clone()));
+                        calculation.moves_to[target_index] = Some(tile);
+                        self.layers.need_update[target_index] = true;
+                    }
+                }
+                Err(target) => {
+                    cross_moves.entry(target).or_insert(CrossMove::Spawn(tile));
+                }
+            }
+        }
+    }
+
+    /// Takes the tile out of the chunk together with the data that moves with it
+    fn take_tile(&mut self, index: usize) -> Option<MovingTile> {
+        let tile_info = self.tile_info[index].take()?;
+        Some(MovingTile {
+            tile_info,
+            layers: self.layers.take(index),
+        })
+    }
+
+    fn is_free(&self, index: usize, calculation: &ChunkCalculation) -> bool {
+        self.tile_info[index].is_none()
+            && calculation.moves_to[index].is_none()
+            && !calculation.awaiting_reply[index]
+    }
+
+    fn can_displace(
+        &self,
+        index: usize,
+        target_material: MaterialId,
+        direction: &TileMoveDirection,
+        materials: &MaterialRegistry,
+    ) -> bool {
+        let material = materials.get(self.tile_info[index].as_ref().unwrap().material);
+        let target = materials.get(target_material);
+        // Only sink, so that fluids don't trade places sideways forever
+        direction.direction().y < 0
+            && target.movement.is_fluid()
+            && material.density > target.density
+    }
+
+    fn swap_tiles(
+        &mut self,
+        index: usize,
+        target_index: usize,
+        direction: TileMoveDirection,
+        calculation: &mut ChunkCalculation,
+        extra_updates: &mut Vec<Tile>,
+    ) {
+        // Register the swap
+        let mut tile = self.take_tile(index).unwrap();
+        tile.register_move(direction, false);
+        let target = self.take_tile(target_index).unwrap();
+        calculation.moves[index] = Some(target_index);
+        calculation.moves[target_index] = Some(index);
+        calculation.moves_from[index] = true;
+        calculation.moves_from[target_index] = true;
+        calculation.moves_to[target_index] = Some(tile.clone());
+        calculation.moves_to[index] = Some(target.clone());
+        self.layers.cant_move[index] = false;
+        self.layers.cant_move[target_index] = false;
+
+        // Update view
+        calculation.view_update[target_index] = Some(Some(tile));
+        calculation.view_update[index] = Some(Some(target));
+
+        // Queue update for the next frame
+        self.layers.need_update[index] = true;
+        self.layers.need_update[target_index] = true;
+
+        // Update nearby lazy tiles
+        self.update_tiles_around(index, 1, calculation, extra_updates);
+        self.update_tiles_around(target_index, 1, calculation, extra_updates);
+        calculation.swaps.push(index);
+        calculation.swaps.push(target_index);
+    }
+
+    /// Checks whether a tile, that cannot move, may be displaced by a denser tile
+    fn displacement_info(
+        &self,
+        index: usize,
+        calculation: &ChunkCalculation,
+        materials: &MaterialRegistry,
+    ) -> MoveInfo {
+        match &self.tile_info[index] {
+            Some(tile_info)
+                if calculation.moves_to[index].is_none()
+                    && materials.get(tile_info.material).movement.is_fluid() =>
+            {
+                MoveInfo::Displaceable(tile_info.material)
+            }
+            _ => MoveInfo::Impossible,
+        }
+    }
+
     fn queue_updates_around(&mut self, index: usize, distance: i32) -> Vec<Tile> {
         let mut extra_updates = Vec::new();
         // Queue updates in a square around a given tile
-        for dx in -distance..=distance {
-            for dy in -distance..=distance {
-                let shift = ivec2(dx, dy);
-                match self.shift_position(index, shift) {
-                    Ok(index) => {
-                        if self.tiles[index] {
-                            self.queue_update(index);
-                        }
-                    }
-                    Err(tile) => {
-                        extra_updates.push(tile);
+        for shift in square(distance) {
+            match self.shift_position(index, shift) {
+                Ok(index) => {
+                    if self.tiles[index] {
+                        self.queue_update(index);
                     }
                 }
+                Err(tile) => {
+                    extra_updates.push(tile);
+                }
             }
         }
         extra_updates
@@ -362,23 +766,23 @@ impl Chunk {
         extra_updates: &mut Vec<Tile>,
     ) {
         // Update tiles in a square around a given tile
-        for dx in -distance..=distance {
-            for dy in -distance..=distance {
-                let shift = ivec2(dx, dy);
-                match self.shift_position(index, shift) {
-                    Ok(index) => {
-                        // Tile is inside the chunk
-                        if self.tiles[index] && !self.need_update[index] {
+        for shift in square(distance) {
+            match self.shift_position(index, shift) {
+                Ok(index) => {
+                    // Tile is inside the chunk, tiles that stay active
+                    // are queued already, but might be able to move now too
+                    if self.tiles[index] {
+                        if !self.layers.need_update[index] {
                             calculation.update_tiles.push(index);
-                            calculation.checked[index] = false;
-                            self.cant_move[index] = false;
                         }
-                    }
-                    Err(tile) => {
-                        // Tile is outside the chunk
-                        extra_updates.push(tile);
+                        calculation.checked[index] = false;
+                        self.layers.cant_move[index] = false;
                     }
                 }
+                Err(tile) => {
+                    // Tile is outside the chunk
+                    extra_updates.push(tile);
+                }
             }
         }
     }
@@ -419,18 +823,466 @@ impl Chunk {
         }
     }
 
-    pub fn movement(&mut self, moves: DataArray<Option<TileInfo>>) {
-        for (index, tile_info) in moves
+    /// Finds a neighbour of a tile and the chunk it's in, neighbours in other chunks
+    /// are looked up in `chunks`, there is none if that chunk doesn't exist
+    fn neighbour<'a>(
+        &'a self,
+        chunks: &'a HashMap<IVec2, Chunk>,
+        index: usize,
+        shift: IVec2,
+    ) -> Option<(&'a Chunk, Tile)> {
+        match self.shift_position(index, shift) {
+            Ok(neighbour_index) => Some((
+                self,
+                Tile {
+                    chunk_pos: self.chunk_pos,
+                    index: neighbour_index,
+                },
+            )),
+            Err(tile) => chunks.get(&tile.chunk_pos).map(|chunk| (chunk, tile)),
+        }
+    }
+
+    pub fn movement(
+        &mut self,
+        moves: DataArray<Option<MovingTile>>,
+        swaps: Vec<usize>,
+    ) -> Vec<Tile> {
+        for (index, tile) in moves
             .into_iter()
             .enumerate()
-            .filter_map(|(index, tile_info)| tile_info.map(|tile_info| (index, tile_info)))
+            .filter_map(|(index, tile)| tile.map(|tile| (index, tile)))
         {
-            self.tile_info[index] = Some(tile_info);
+            self.tile_info[index] = Some(tile.tile_info);
+            self.layers.set(index, tile.layers);
         }
 
         for (index, tile) in self.tile_info.iter().enumerate() {
             self.tiles[index] = tile.is_some();
         }
+
+        // Tiles around swaps might be able to move next frame
+        let mut extra_updates = Vec::new();
+        for index in swaps {
+            extra_updates.extend(self.queue_updates_around(index, 1));
+        }
+        extra_updates
+    }
+
+    /// Calculates temperatures after heat has flowed between neighbouring tiles,
+    /// neighbours in other chunks are looked up in `chunks`
+    pub fn heat_flow(
+        &self,
+        chunks: &HashMap<IVec2, Chunk>,
+        materials: &MaterialRegistry,
+    ) -> DataArray<f32> {
+        let mut temperature = self.layers.temperature.clone();
+        for (index, tile_info) in self
+            .tiles()
+            .filter_map(|(index, tile_info)| tile_info.as_ref().map(|tile_info| (index, tile_info)))
+        {
+            let material = materials.get(tile_info.material);
+            for shift in &[ivec2(1, 0), ivec2(-1, 0), ivec2(0, 1), ivec2(0, -1)] {
+                let (chunk, neighbour) = match self.neighbour(chunks, index, *shift) {
+                    Some(neighbour) => neighbour,
+                    None => continue,
+                };
+
+                // Heat only flows between tiles, empty space is an insulator
+                if let Some(neighbour_info) = &chunk.tile_info[neighbour.index] {
+                    let conductivity = material
+                        .conductivity
+                        .min(materials.get(neighbour_info.material).conductivity);
+                    let heat = HEAT_FLOW_RATE
+                        * conductivity
+                        * (chunk.layers.temperature[neighbour.index]
+                            - self.layers.temperature[index]);
+                    temperature[index] += heat / material.heat_capacity;
+                }
+            }
+        }
+        temperature
+    }
+
+    /// Finds active tiles that fall faster than one tile per tick
+    pub fn fast_tiles(&self) -> Vec<usize> {
+        self.tiles()
+            .filter_map(|(index, tile_info)| match tile_info {
+                Some(_) if self.layers.need_update[index] && self.layers.speed(index) > 1 => {
+                    Some(index)
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Lets charges fade by one tick, returns the tiles that pass a charge on
+    /// and the tiles that have lost their charge
+    pub fn discharge(&mut self, materials: &MaterialRegistry) -> (Vec<usize>, Vec<usize>) {
+        let mut sources = Vec::new();
+        let mut discharged = Vec::new();
+        for (index, tile_info) in self.tile_info.iter_mut().enumerate() {
+            if let Some(tile_info) = tile_info {
+                let material = materials.get(tile_info.material);
+                if material.flags.power
+                    || (material.flags.conductor && self.layers.charge[index] == CHARGE_TICKS)
+                {
+                    sources.push(index);
+                }
+                if self.layers.charge[index] > 0 {
+                    self.layers.charge[index] -= 1;
+                    if self.layers.charge[index] == 0 {
+                        discharged.push(index);
+                    }
+                }
+            }
+        }
+        (sources, discharged)
+    }
+
+    /// Finds active tiles that can grow and touch the material they consume,
+    /// neighbours in other chunks are looked up in `chunks`
+    pub fn growth_candidates(
+        &self,
+        chunks: &HashMap<IVec2, Chunk>,
+        materials: &MaterialRegistry,
+    ) -> Vec<(usize, Tile)> {
+        let mut candidates = Vec::new();
+        for (index, tile_info) in self
+            .tiles()
+            .filter_map(|(index, tile_info)| match tile_info {
+                Some(tile_info)
+                    if self.layers.need_update[index]
+                        && self.tile(index).unwrap().can_grow(materials) =>
+                {
+                    Some((index, tile_info))
+                }
+                _ => None,
+            })
+        {
+            let consumes = materials.get(tile_info.material).growth.unwrap().consumes;
+            for shift in &[ivec2(1, 0), ivec2(-1, 0), ivec2(0, 1), ivec2(0, -1)] {
+                let (chunk, neighbour) = match self.neighbour(chunks, index, *shift) {
+                    Some(neighbour) => neighbour,
+                    None => continue,
+                };
+
+                if matches!(&chunk.tile_info[neighbour.index], Some(neighbour_info) if neighbour_info.material == consumes)
+                {
+                    candidates.push((index, neighbour));
+                    break;
+                }
+            }
+        }
+        candidates
+    }
+
+    pub fn has_life(&self, materials: &MaterialRegistry) -> bool {
+        self.tile_info
+            .iter()
+            .flatten()
+            .any(|tile_info| materials.get(tile_info.material).life.is_some())
+    }
+
+    /// Calculates the next generation of tiles that follow a life rule from the current one,
+    /// neighbours in other chunks are looked up in `chunks`,
+    /// returns tiles that are born with their material and tiles that die
+    pub fn life_step(
+        &self,
+        chunks: &HashMap<IVec2, Chunk>,
+        materials: &MaterialRegistry,
+    ) -> Vec<(usize, Option<MaterialId>)> {
+        let mut changes = Vec::new();
+        for (index, tile_info) in self.tiles() {
+            // Count neighbours by material, there are at most 8 different ones
+            let mut neighbours: Vec<(MaterialId, usize)> = Vec::with_capacity(8);
+            for dx in -1..=1 {
+                for dy in -1..=1 {
+                    if dx == 0 && dy == 0 {
+                        continue;
+                    }
+                    let neighbour_info = match self.neighbour(chunks, index, ivec2(dx, dy)) {
+                        Some((chunk, neighbour)) => &chunk.tile_info[neighbour.index],
+                        None => continue,
+                    };
+                    if let Some(neighbour_info) = neighbour_info {
+                        match neighbours
+                            .iter_mut()
+                            .find(|(material, _)| *material == neighbour_info.material)
+                        {
+                            Some((_, count)) => *count += 1,
+                            None => neighbours.push((neighbour_info.material, 1)),
+                        }
+                    }
+                }
+            }
+
+            match tile_info {
+                Some(tile_info) => {
+                    if let Some(rule) = materials.get(tile_info.material).life {
+                        let count = neighbours
+                            .iter()
+                            .find(|(material, _)| *material == tile_info.material)
+                            .map_or(0, |(_, count)| *count);
+                        if !rule.survival[count] {
+                            changes.push((index, None));
+                        }
+                    }
+                }
+                None => {
+                    // The material with the lowest id wins if several could be born
+                    neighbours.sort_unstable();
+                    if let Some((material, _)) =
+                        neighbours.into_iter().find(|&(material, count)| {
+                            materials
+                                .get(material)
+                                .life
+                                .is_some_and(|rule| rule.birth[count])
+                        })
+                    {
+                        changes.push((index, Some(material)));
+                    }
+                }
+            }
+        }
+        changes
+    }
+
+    /// Finds all tiles that are welded into a rigid body
+    pub fn body_tiles(&self) -> Vec<(usize, BodyId)> {
+        self.tiles()
+            .filter_map(|(index, tile_info)| {
+                tile_info
+                    .as_ref()
+                    .and(self.layers.body[index])
+                    .map(|body| (index, body))
+            })
+            .collect()
+    }
+
+    /// Finds all liquid tiles, active or not
+    pub fn liquid_tiles(&self, materials: &MaterialRegistry) -> Vec<usize> {
+        self.tiles()
+            .filter_map(|(index, tile_info)| match tile_info {
+                Some(tile_info)
+                    if materials.get(tile_info.material).movement == MovementRule::Liquid =>
+                {
+                    Some(index)
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Finds active tiles that spread sideways faster than one tile per tick
+    pub fn spreading_tiles(&self, materials: &MaterialRegistry) -> Vec<usize> {
+        self.tiles()
+            .filter_map(|(index, tile_info)| match tile_info {
+                Some(tile_info)
+                    if self.layers.need_update[index]
+                        && self.layers.spreading[index]
+                        && materials.get(tile_info.material).dispersion > 1 =>
+                {
+                    Some(index)
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Ages active decaying tiles by one tick, returns the ones that have expired
+    pub fn age_tiles(&mut self, materials: &MaterialRegistry) -> Vec<usize> {
+        let mut expired = Vec::new();
+        for (index, tile_info) in self.tile_info.iter().enumerate() {
+            if let Some(tile_info) = tile_info {
+                if let (true, Some(lifetime)) = (
+                    self.layers.need_update[index],
+                    materials.get(tile_info.material).lifetime,
+                ) {
+                    self.layers.age[index] += 1;
+                    if self.layers.age[index] >= lifetime {
+                        expired.push(index);
+                    }
+                }
+            }
+        }
+        expired
+    }
+
+    pub fn flying_tiles(&self) -> Vec<usize> {
+        self.tiles()
+            .filter_map(|(index, tile_info)| match tile_info {
+                Some(_)
+                    if self.layers.need_update[index] && self.layers.flight[index].is_some() =>
+                {
+                    Some(index)
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn burning_tiles(&self) -> Vec<usize> {
+        self.tiles()
+            .filter_map(|(index, tile_info)| match tile_info {
+                Some(_)
+                    if self.layers.need_update[index] && self.layers.burning[index].is_some() =>
+                {
+                    Some(index)
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Finds active tiles that have a reaction with a neighbour,
+    /// neighbours in other chunks are looked up in `chunks`
+    pub fn reaction_candidates(
+        &self,
+        chunks: &HashMap<IVec2, Chunk>,
+        materials: &MaterialRegistry,
+    ) -> Vec<(usize, Tile)> {
+        let mut candidates = Vec::new();
+        for (index, tile_info) in self
+            .tiles()
+            .filter_map(|(index, tile_info)| match tile_info {
+                Some(tile_info)
+                    if self.layers.need_update[index] || self.layers.reacting[index] =>
+                {
+                    Some((index, tile_info))
+                }
+                _ => None,
+            })
+        {
+            for shift in &[ivec2(1, 0), ivec2(-1, 0), ivec2(0, 1), ivec2(0, -1)] {
+                let (chunk, neighbour) = match self.neighbour(chunks, index, *shift) {
+                    Some(neighbour) => neighbour,
+                    None => continue,
+                };
+
+                if let Some(neighbour_info) = &chunk.tile_info[neighbour.index] {
+                    if materials
+                        .reaction(tile_info.material, neighbour_info.material)
+                        .is_some()
+                    {
+                        candidates.push((index, neighbour));
+                    }
+                }
+            }
+        }
+        candidates
+    }
+
+    /// Finds active tiles next to a tile they absorb or that absorbs them,
+    /// returns pairs of the porous tile and the absorbed one
+    pub fn absorption_candidates(
+        &self,
+        chunks: &HashMap<IVec2, Chunk>,
+        materials: &MaterialRegistry,
+    ) -> Vec<(Tile, Tile)> {
+        self.neighbour_pairs(chunks, |material, absorbed| {
+            materials
+                .get(material)
+                .absorption
+                .is_some_and(|absorption| absorption.absorbs == absorbed)
+        })
+    }
+
+    /// Finds active tiles next to a solvent they dissolve in or a tile that dissolves in them,
+    /// returns pairs of the soluble tile and the solvent
+    pub fn solution_candidates(
+        &self,
+        chunks: &HashMap<IVec2, Chunk>,
+        materials: &MaterialRegistry,
+    ) -> Vec<(Tile, Tile)> {
+        self.neighbour_pairs(chunks, |material, solvent| {
+            materials
+                .get(material)
+                .solubility
+                .is_some_and(|solubility| solubility.solvent == solvent)
+        })
+    }
+
+    /// Finds pairs of an active tile and a neighbour of materials matching `pairs`,
+    /// in either order, neighbours in other chunks are looked up in `chunks`
+    fn neighbour_pairs(
+        &self,
+        chunks: &HashMap<IVec2, Chunk>,
+        pairs: impl Fn(MaterialId, MaterialId) -> bool,
+    ) -> Vec<(Tile, Tile)> {
+        let mut candidates = Vec::new();
+        for (index, tile_info) in self
+            .tiles()
+            .filter_map(|(index, tile_info)| match tile_info {
+                Some(tile_info) if self.layers.need_update[index] => Some((index, tile_info)),
+                _ => None,
+            })
+        {
+            let tile = Tile {
+                chunk_pos: self.chunk_pos,
+                index,
+            };
+            for shift in square(1) {
+                let (chunk, neighbour) = match self.neighbour(chunks, index, shift) {
+                    Some((_, neighbour)) if neighbour == tile => continue,
+                    Some(neighbour) => neighbour,
+                    None => continue,
+                };
+
+                if let Some(neighbour_info) = &chunk.tile_info[neighbour.index] {
+                    if pairs(tile_info.material, neighbour_info.material) {
+                        candidates.push((tile, neighbour));
+                    } else if pairs(neighbour_info.material, tile_info.material) {
+                        candidates.push((neighbour, tile));
+                    }
+                }
+            }
+        }
+        candidates
+    }
+
+    /// Finds tiles holding a solute that are about to evaporate
+    pub fn precipitation_candidates(&self, materials: &MaterialRegistry) -> Vec<usize> {
+        (0..self.tile_info.len())
+            .filter(|&index| self.precipitates(index, materials))
+            .collect()
+    }
+
+    pub fn precipitates(&self, index: usize, materials: &MaterialRegistry) -> bool {
+        match &self.tile_info[index] {
+            Some(tile_info) if self.layers.solute[index].is_some() => materials
+                .get(tile_info.material)
+                .transition(self.layers.temperature[index])
+                .is_some_and(|into| matches!(materials.get(into).movement, MovementRule::Gas)),
+            _ => false,
+        }
+    }
+
+    /// Turns tiles into other materials when their temperature crosses a threshold,
+    /// returns changed tiles and updates for other chunks
+    pub fn apply_transitions(&mut self, materials: &MaterialRegistry) -> (Vec<usize>, Vec<Tile>) {
+        let mut changed = Vec::new();
+        let mut extra_updates = Vec::new();
+        for index in 0..self.tile_info.len() {
+            let into = match &self.tile_info[index] {
+                Some(tile_info) => materials
+                    .get(tile_info.material)
+                    .transition(self.layers.temperature[index]),
+                None => None,
+            };
+
+            if let Some(into) = into {
+                let tile_info = self.tile_info[index].as_mut().unwrap();
+                tile_info.material = into;
+                // Absorbed liquid doesn't survive the transition
+                self.layers.saturation[index] = 0;
+                changed.push(index);
+
+                // The new material might move differently
+                extra_updates.extend(self.queue_updates_around(index, 1));
+            }
+        }
+        (changed, extra_updates)
     }
 }
 
@@ -440,15 +1292,173 @@ pub enum MoveInfo {
     Impossible,
     Recursive,
     Possible,
+    /// The tile cannot move, but it can swap with a denser one
+    Displaceable(MaterialId),
+}
+
+/// A tile together with the data that moves with it
+#[derive(Clone, Debug)]
+pub struct MovingTile {
+    pub tile_info: TileInfo,
+    pub layers: LayerValues,
+}
+
+impl MovingTile {
+    /// Creates a tile of a material at the material's temperature
+    pub fn new(material: MaterialId, materials: &MaterialRegistry) -> Self {
+        Self {
+            tile_info: TileInfo::new(material),
+            layers: LayerValues {
+                temperature: materials.get(material).temperature,
+                ..LayerValues::default()
+            },
+        }
+    }
+
+    /// Creates a tile as it is placed, burning materials are placed already burning,
+    /// the shade is drawn from the random numbers of the tile
+    pub fn spawn(
+        material: MaterialId,
+        materials: &MaterialRegistry,
+        mut random: TileRandom,
+    ) -> Self {
+        let mut tile = Self::new(material, materials);
+        tile.layers.shade = random.next_u64() as u8;
+        let material = materials.get(material);
+        if material.flags.burning {
+            tile.layers.burning = Some(material.burn_time);
+        }
+        tile
+    }
+
+    pub fn is_burning(&self) -> bool {
+        self.layers.burning.is_some()
+    }
+
+    pub fn is_charged(&self) -> bool {
+        self.layers.charge > 0
+    }
+
+    pub fn is_flying(&self) -> bool {
+        self.layers.flight.is_some()
+    }
+
+    pub fn can_grow(&self, materials: &MaterialRegistry) -> bool {
+        materials.get(self.tile_info.material).growth.is_some() && !self.layers.grown
+    }
+
+    /// Checks whether the tile has to stay updated even when it can't move
+    pub fn stays_active(&self, materials: &MaterialRegistry) -> bool {
+        self.is_burning()
+            || self.is_flying()
+            || self.tile_info.is_decaying(materials)
+            || self.tile_info.is_emitter(materials)
+            || self.can_grow(materials)
+    }
+
+    /// Returns directions the tile tries to move in, in order,
+    /// `mirrored` swaps the order of the diagonal ones,
+    /// a tile drifting with the wind falls towards it first and is pushed sideways
+    pub fn movement_directions(
+        &self,
+        materials: &MaterialRegistry,
+        mirrored: bool,
+        drift: Option<HorizontalMove>,
+    ) -> Vec<TileMoveDirection> {
+        // Flying tiles are moved by their flight
+        if self.is_flying() {
+            return vec![];
+        }
+
+        let (side, priority) = match drift {
+            Some(drift) => (drift.to_direction().direction().x, drift),
+            None => (if mirrored { 1 } else { -1 }, self.layers.priority),
+        };
+        let movement = materials.get(self.tile_info.material).movement;
+        let mut directions = match movement {
+            // Rigid bodies are moved as a whole, not tile by tile
+            MovementRule::Static | MovementRule::Rigid => return vec![],
+            MovementRule::Powder => vec![
+                ivec2(0, -1).into(),
+                ivec2(side, -1).into(),
+                ivec2(-side, -1).into(),
+            ],
+            MovementRule::Liquid => vec![
+                ivec2(0, -1).into(),
+                ivec2(side, -1).into(),
+                ivec2(-side, -1).into(),
+                priority.to_direction(),
+                priority.opposite().to_direction(),
+            ],
+            MovementRule::Gas => vec![
+                ivec2(0, 1).into(),
+                ivec2(side, 1).into(),
+                ivec2(-side, 1).into(),
+                priority.to_direction(),
+                priority.opposite().to_direction(),
+            ],
+        };
+
+        // Liquid and gas already spread towards the wind, powder is blown along the ground
+        if let Some(drift) = drift {
+            directions.swap(0, 1);
+            if movement == MovementRule::Powder {
+                directions.push(drift.to_direction());
+            }
+        }
+        directions
+    }
+
+    pub fn register_move(&mut self, tile_move: TileMoveDirection, falling: bool) {
+        self.layers.spreading = match HorizontalMove::from_tile_move(tile_move) {
+            Some(hor_move) => {
+                self.layers.priority = hor_move;
+                true
+            }
+            None => false,
+        };
+
+        // Falling tiles speed up, any other move stops the fall
+        self.layers.velocity = if falling {
+            (self.layers.velocity + GRAVITY).min(TERMINAL_VELOCITY)
+        } else {
+            0.0
+        };
+    }
+}
+
+/// A message to a tile in another chunk
+#[derive(Clone, Debug)]
+pub enum CrossMove {
+    /// A tile moving in, the other chunk replies to `origin` whether it's been placed,
+    /// a swapping tile displaces the target one, which is sent to `origin` instead
+    Move {
+        tile: MovingTile,
+        origin: Tile,
+        swap: bool,
+    },
+    /// A tile from an emitter, it's dropped if the target is taken
+    Spawn(MovingTile),
+    /// The tile that moved away from here has been placed
+    Placed,
+    /// The tile that moved away from here, or the one it displaced, is put back here
+    Returned(MovingTile),
 }
 
 pub struct ChunkCalculation {
     checked: DataArray<bool>,
     moves_from: DataArray<bool>,
     moves: DataArray<Option<usize>>,
-    pub moves_to: DataArray<Option<TileInfo>>,
+    pub moves_to: DataArray<Option<MovingTile>>,
     update_tiles: Vec<usize>,
     unknown: DataArray<bool>,
+    /// Tiles that moved into another chunk, kept free until it places or returns the tile
+    awaiting_reply: DataArray<bool>,
+    pub swaps: Vec<usize>,
     pub dependencies: DataArray<Option<Tile>>,
-    pub view_update: DataArray<Option<Option<TileInfo>>>,
+    /// Tile in another chunk that an unknown tile waits for,
+    /// either itself or through the tiles it wants to move into
+    pub waits_on: DataArray<Option<Tile>>,
+    pub view_update: DataArray<Option<Option<MovingTile>>>,
+    emitted: bool,
 }
@@ -0,0 +1,33 @@
+use rayon::prelude::*;
+
+use super::{calculator::ViewUpdates, tile::Tile, World};
+
+impl World {
+    /// Ages decaying tiles and turns the expired ones into their decay product
+    pub fn decay(&mut self, view_update: &mut ViewUpdates) {
+        let materials = &self.materials;
+        let mut expired = self
+            .chunks
+            .par_iter_mut()
+            .flat_map(|(&chunk_pos, chunk)| {
+                chunk
+                    .age_tiles(materials)
+                    .into_par_iter()
+                    .map(move |index| Tile { chunk_pos, index })
+            })
+            .collect::<Vec<_>>();
+        expired.sort_by_key(|tile| (tile.chunk_pos.x, tile.chunk_pos.y, tile.index));
+
+        for tile in expired {
+            let chunk = &self.chunks[&tile.chunk_pos];
+            let material = chunk.tile_info[tile.index].as_ref().unwrap().material;
+            let temperature = chunk.layers.temperature[tile.index];
+            self.replace_tile(
+                tile,
+                self.materials.get(material).decay,
+                temperature,
+                view_update,
+            );
+        }
+    }
+}
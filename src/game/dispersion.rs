@@ -0,0 +1,58 @@
+use rayon::prelude::*;
+
+use super::{calculator::ViewUpdates, tile::Tile, tile_move::TileMove, World};
+
+impl World {
+    /// Moves spreading tiles further sideways, as far as their dispersion allows
+    pub fn disperse(&mut self, view_update: &mut ViewUpdates) {
+        let materials = &self.materials;
+        let gravity = &self.gravity;
+        let mut spreading_tiles = self
+            .chunks
+            .par_iter()
+            .flat_map(|(&chunk_pos, chunk)| {
+                chunk
+                    .spreading_tiles(materials)
+                    .into_par_iter()
+                    .map(move |index| {
+                        let tile = Tile { chunk_pos, index };
+                        let moving_tile = chunk.tile(index).unwrap();
+                        let gravity = gravity.at(tile.global_position());
+                        (
+                            tile,
+                            gravity.rotate(moving_tile.layers.priority.to_direction().direction()),
+                            gravity.rotate(
+                                moving_tile.movement_directions(materials, false, None)[0]
+                                    .direction(),
+                            ),
+                            materials.get(moving_tile.tile_info.material).dispersion,
+                        )
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        // The front of a spreading stream moves first, otherwise it would block the tiles
+        // flowing in the same direction behind it
+        spreading_tiles.sort_by_key(|(tile, direction, _, _)| {
+            let position = tile.global_position();
+            (-position.dot(*direction), position.y, position.x)
+        });
+
+        for (tile, direction, fall_direction, dispersion) in spreading_tiles {
+            // Its first sideways step in the calculation counts towards the dispersion
+            let mut target = tile;
+            for _ in 1..dispersion {
+                // Stop where the tile can fall instead
+                let next = target.shifted(direction);
+                if !self.is_free(next) || self.is_free(target.shifted(fall_direction)) {
+                    break;
+                }
+                target = next;
+            }
+
+            if target != tile {
+                self.move_tile(tile, target, view_update);
+            }
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use macroquad::prelude::ivec2;
+use rayon::prelude::*;
+
+use crate::constants::CHARGE_TICKS;
+
+use super::{calculator::ViewUpdates, tile::Tile, World};
+
+impl World {
+    /// Passes charges on to neighbouring tiles, which are powered when they take a charge
+    pub fn conduct(&mut self, view_update: &mut ViewUpdates) {
+        let materials = &self.materials;
+        let mut discharges = self
+            .chunks
+            .par_iter_mut()
+            .map(|(&chunk_pos, chunk)| (chunk_pos, chunk.discharge(materials)))
+            .collect::<Vec<_>>();
+        discharges.sort_by_key(|(chunk_pos, _)| (chunk_pos.x, chunk_pos.y));
+
+        let mut sources = Vec::new();
+        let mut switched_off = Vec::new();
+        for (chunk_pos, (chunk_sources, discharged)) in discharges {
+            // Show tiles that have lost their charge
+            let chunk = &self.chunks[&chunk_pos];
+            if let Some(view_update) = view_update.get_mut(&chunk_pos) {
+                for &index in &discharged {
+                    view_update[index] = Some(chunk.tile(index));
+                }
+            }
+            switched_off.extend(
+                discharged
+                    .into_iter()
+                    .map(|index| Tile { chunk_pos, index }),
+            );
+
+            sources.extend(
+                chunk_sources
+                    .into_iter()
+                    .map(|index| Tile { chunk_pos, index }),
+            );
+        }
+
+        // Toggled tiles turn back once their charge has run out,
+        // a tile that is still powered is charged and toggled again right away
+        for tile in switched_off {
+            let material = self.material_at(tile).unwrap();
+            if let Some(toggle) = self.materials.get(material).toggle {
+                let temperature = self.chunks[&tile.chunk_pos].layers.temperature[tile.index];
+                self.replace_tile(tile, Some(toggle), temperature, view_update);
+            }
+        }
+
+        for tile in sources {
+            for shift in &[ivec2(1, 0), ivec2(-1, 0), ivec2(0, 1), ivec2(0, -1)] {
+                self.charge(tile.shifted(*shift), view_update);
+            }
+        }
+    }
+
+    /// Charges a tile if it can take a charge, then heats it or toggles it on
+    fn charge(&mut self, tile: Tile, view_update: &mut ViewUpdates) {
+        let chunk = match self.chunks.get_mut(&tile.chunk_pos) {
+            Some(chunk) => chunk,
+            None => return,
+        };
+        let tile_info = match &chunk.tile_info[tile.index] {
+            Some(tile_info) => tile_info,
+            None => return,
+        };
+        let material = self.materials.get(tile_info.material);
+        if chunk.layers.charge[tile.index] > 0 || !material.is_electric() {
+            return;
+        }
+
+        chunk.layers.charge[tile.index] = CHARGE_TICKS;
+        chunk.layers.temperature[tile.index] += material.electric_heat;
+        if let Some(view_update) = view_update.get_mut(&tile.chunk_pos) {
+            view_update[tile.index] = Some(chunk.tile(tile.index));
+        }
+
+        if let Some(toggle) = material.toggle {
+            let temperature = chunk.layers.temperature[tile.index];
+            self.replace_tile(tile, Some(toggle), temperature, view_update);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        constants::MATERIALS_PATH,
+        game::{gravity::GravityField, material::MaterialRegistry},
+    };
+
+    #[test]
+    fn powered_lamps_stay_lit() {
+        let materials = MaterialRegistry::load(MATERIALS_PATH).unwrap();
+        let mut world = World::new(materials, GravityField::default(), 0);
+        world.place("battery", ivec2(10, 10));
+        world.place("metal", ivec2(11, 10));
+        world.place("lamp", ivec2(12, 10));
+
+        for tick in 0..20 {
+            world.tick();
+            if tick >= 2 {
+                assert_eq!(world.find("lit lamp"), vec![ivec2(12, 10)]);
+            }
+        }
+
+        // The lamp goes out once the battery is gone
+        world.set_tile(Tile::from_global_position(ivec2(10, 10)), None);
+        for _ in 0..2 * CHARGE_TICKS {
+            world.tick();
+        }
+        assert_eq!(world.find("lamp"), vec![ivec2(12, 10)]);
+    }
+}
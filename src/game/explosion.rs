@@ -0,0 +1,226 @@
+use macroquad::prelude::{ivec2, vec2, IVec2, Vec2};
+use rayon::prelude::*;
+
+use crate::constants::{BLAST_SPEED, GRAVITY, TERMINAL_VELOCITY};
+
+use super::{calculator::ViewUpdates, material::MovementRule, tile::Tile, World};
+
+impl World {
+    pub fn detonate(&mut self, view_update: &mut ViewUpdates) {
+        // Find burning explosives in parallel
+        let materials = &self.materials;
+        let mut explosives = self
+            .chunks
+            .par_iter()
+            .map(|(&chunk_pos, chunk)| {
+                let explosives = chunk
+                    .burning_tiles()
+                    .into_iter()
+                    .filter(|&index| {
+                        let material = chunk.tile_info[index].as_ref().unwrap().material;
+                        materials.get(material).blast_radius.is_some()
+                    })
+                    .collect::<Vec<_>>();
+                (chunk_pos, explosives)
+            })
+            .collect::<Vec<_>>();
+        explosives.sort_by_key(|(chunk_pos, _)| (chunk_pos.x, chunk_pos.y));
+
+        for (chunk_pos, explosives) in explosives {
+            for index in explosives {
+                let tile = Tile { chunk_pos, index };
+                // The tile might have been destroyed by another blast already
+                let radius = match self.material_at(tile) {
+                    Some(material) => match self.materials.get(material).blast_radius {
+                        Some(radius) => radius,
+                        None => continue,
+                    },
+                    None => continue,
+                };
+                let temperature = self.chunks[&chunk_pos].layers.temperature[index];
+                self.replace_tile(tile, None, temperature, view_update);
+                self.blast(tile.global_position(), radius, view_update);
+            }
+        }
+
+        for (tile, radius) in std::mem::take(&mut self.blasts) {
+            self.blast(tile.global_position(), radius, view_update);
+        }
+    }
+
+    /// Destroys tiles around a position that can't withstand the blast, flings loose
+    /// tiles away and ignites explosives, the strength of the blast falls off with distance
+    fn blast(&mut self, center: IVec2, radius: f32, view_update: &mut ViewUpdates) {
+        let extent = radius.ceil() as i32;
+        for dx in -extent..=extent {
+            for dy in -extent..=extent {
+                let distance = ((dx * dx + dy * dy) as f32).sqrt();
+                if distance > radius {
+                    continue;
+                }
+
+                let tile = Tile::from_global_position(center + ivec2(dx, dy));
+                let material = match self.material_at(tile) {
+                    Some(material) => self.materials.get(material),
+                    None => continue,
+                };
+                let strength = 1.0 - distance / radius;
+                if strength <= material.blast_resistance {
+                    continue;
+                }
+
+                let temperature = self.chunks[&tile.chunk_pos].layers.temperature[tile.index];
+                let chunk = self.chunks.get_mut(&tile.chunk_pos).unwrap();
+                if material.blast_radius.is_some() {
+                    // Explosives go off with the next detonation
+                    if chunk.layers.burning[tile.index].is_none() {
+                        chunk.layers.burning[tile.index] = Some(material.burn_time);
+                        chunk.queue_update(tile.index);
+                        if let Some(view_update) = view_update.get_mut(&tile.chunk_pos) {
+                            view_update[tile.index] = Some(chunk.tile(tile.index));
+                        }
+                    }
+                } else if let MovementRule::Static | MovementRule::Rigid = material.movement {
+                    let debris = material.debris;
+                    self.replace_tile(tile, debris, temperature, view_update);
+                    if debris.is_some() {
+                        self.fling(tile, vec2(dx as f32, dy as f32), strength);
+                    }
+                } else {
+                    self.fling(tile, vec2(dx as f32, dy as f32), strength);
+                }
+            }
+        }
+    }
+
+    /// Launches a loose tile away from the center of a blast
+    fn fling(&mut self, tile: Tile, offset: Vec2, strength: f32) {
+        let chunk = self.chunks.get_mut(&tile.chunk_pos).unwrap();
+        let tile_info = chunk.tile_info[tile.index].as_ref().unwrap();
+        // Debris might have been a solid material as well
+        if let MovementRule::Static | MovementRule::Rigid =
+            self.materials.get(tile_info.material).movement
+        {
+            return;
+        }
+
+        let direction = if offset == vec2(0.0, 0.0) {
+            -self.gravity.at(tile.global_position()).down()
+        } else {
+            offset.normalize()
+        };
+        chunk.layers.flight[tile.index] = Some(direction * strength * BLAST_SPEED);
+        chunk.layers.spreading[tile.index] = false;
+        chunk.layers.body[tile.index] = None;
+        chunk.layers.velocity[tile.index] = 0.0;
+        chunk.queue_update(tile.index);
+    }
+
+    /// Moves flying tiles along their velocity through free space,
+    /// gravity pulls them down until they hit something
+    pub fn fly(&mut self, view_update: &mut ViewUpdates) {
+        let mut flying = self
+            .chunks
+            .par_iter()
+            .flat_map(|(&chunk_pos, chunk)| {
+                chunk.flying_tiles().into_par_iter().map(move |index| {
+                    (
+                        Tile { chunk_pos, index },
+                        chunk.layers.flight[index].unwrap(),
+                    )
+                })
+            })
+            .collect::<Vec<_>>();
+        flying.sort_by_key(|(tile, _)| {
+            let position = tile.global_position();
+            (position.x, position.y)
+        });
+
+        for (tile, flight) in flying {
+            // Follow the line the tile flies along this tick
+            let start = tile.global_position();
+            let steps = flight.x.abs().max(flight.y.abs()).ceil() as i32;
+            let mut target = tile;
+            let mut blocked = false;
+            for step in 1..=steps {
+                let offset = (flight * step as f32 / steps as f32).round();
+                let next =
+                    Tile::from_global_position(start + ivec2(offset.x as i32, offset.y as i32));
+                if next == target {
+                    continue;
+                }
+                if self.is_free(next) {
+                    target = next;
+                } else {
+                    blocked = true;
+                    break;
+                }
+            }
+
+            if target != tile {
+                self.move_tile(tile, target, view_update);
+            }
+
+            // The tile lands when it hits something, until then it is pulled down
+            // and carried by the wind, a wind of strength 1 pulls as strongly as gravity
+            let position = target.global_position();
+            let down = self.gravity.at(position).down();
+            let wind = self.wind_at(position);
+            let chunk = self.chunks.get_mut(&target.chunk_pos).unwrap();
+            let drag = self
+                .materials
+                .get(chunk.tile_info[target.index].as_ref().unwrap().material)
+                .drag;
+            chunk.layers.flight[target.index] = if blocked {
+                None
+            } else {
+                let flight = flight + (down + wind * drag) * GRAVITY;
+                let falling_speed = flight.dot(down);
+                if falling_speed > TERMINAL_VELOCITY {
+                    Some(flight - down * (falling_speed - TERMINAL_VELOCITY))
+                } else {
+                    Some(flight)
+                }
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{gravity::GravityField, material::MaterialRegistry};
+
+    const MATERIALS: &str = r#"
+        [[material]]
+        name = "stone"
+        color = [130, 130, 130]
+        movement = "static"
+
+        [[material]]
+        name = "tnt"
+        color = [200, 40, 40]
+        movement = "static"
+        burn_time = 0
+        blast_radius = 3.0
+    "#;
+
+    #[test]
+    fn explosives_that_burn_out_at_once_still_go_off() {
+        let materials = MaterialRegistry::from_toml(MATERIALS).unwrap();
+        let mut world = World::new(materials, GravityField::default(), 0);
+        // The first explosive sets off the second one, which is the only one reaching the stone
+        world.place("tnt", ivec2(10, 10));
+        world.place("tnt", ivec2(12, 10));
+        world.place("stone", ivec2(14, 10));
+        world
+            .blasts
+            .push((Tile::from_global_position(ivec2(10, 10)), 1.0));
+
+        for _ in 0..3 {
+            world.tick();
+        }
+        assert_eq!(world.find("tnt"), vec![]);
+        assert!(world.find("stone").is_empty());
+    }
+}
@@ -0,0 +1,52 @@
+use rayon::prelude::*;
+
+use super::{calculator::ViewUpdates, tile::Tile, World};
+
+impl World {
+    /// Moves fast tiles further along gravity, as far as there is free space
+    pub fn fall(&mut self, view_update: &mut ViewUpdates) {
+        let gravity = &self.gravity;
+        let mut fast_tiles = self
+            .chunks
+            .par_iter()
+            .flat_map(|(&chunk_pos, chunk)| {
+                chunk.fast_tiles().into_par_iter().map(move |index| {
+                    let tile = Tile { chunk_pos, index };
+                    let direction = gravity.at(tile.global_position()).direction();
+                    (tile, direction, chunk.layers.speed(index))
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // Tiles that are ahead move first, so the ones behind them can follow
+        fast_tiles.sort_by_key(|(tile, direction, _)| {
+            let position = tile.global_position();
+            (-position.dot(*direction), position.x, position.y)
+        });
+
+        for (tile, direction, speed) in fast_tiles {
+            // The tile has already moved one tile during the calculation
+            let mut target = tile;
+            let mut blocked = false;
+            for _ in 1..speed {
+                let next = target.shifted(direction);
+                if self.is_free(next) {
+                    target = next;
+                } else {
+                    blocked = true;
+                    break;
+                }
+            }
+
+            if target != tile {
+                self.move_tile(tile, target, view_update);
+            }
+
+            // The tile has hit something
+            if blocked {
+                let chunk = self.chunks.get_mut(&target.chunk_pos).unwrap();
+                chunk.layers.velocity[target.index] = 0.0;
+            }
+        }
+    }
+}
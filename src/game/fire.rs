@@ -0,0 +1,86 @@
+use macroquad::prelude::ivec2;
+use rayon::prelude::*;
+
+use crate::constants::SMOKE_CHANCE;
+
+use super::{calculator::ViewUpdates, random::RandomStream, tile::Tile, World};
+
+impl World {
+    pub fn burn(&mut self, view_update: &mut ViewUpdates) {
+        // Find burning tiles in parallel
+        let mut burning = self
+            .chunks
+            .par_iter()
+            .map(|(&chunk_pos, chunk)| (chunk_pos, chunk.burning_tiles()))
+            .collect::<Vec<_>>();
+        burning.sort_by_key(|(chunk_pos, _)| (chunk_pos.x, chunk_pos.y));
+
+        for (chunk_pos, burning) in burning {
+            for index in burning {
+                self.burn_tile(Tile { chunk_pos, index }, view_update);
+            }
+        }
+    }
+
+    fn burn_tile(&mut self, tile: Tile, view_update: &mut ViewUpdates) {
+        let chunk = self.chunks.get_mut(&tile.chunk_pos).unwrap();
+        let temperature = chunk.layers.temperature[tile.index];
+        // The tile might have been consumed already
+        let (material, ticks_left) = match &chunk.tile_info[tile.index] {
+            Some(tile_info) => match &mut chunk.layers.burning[tile.index] {
+                Some(ticks_left) => {
+                    *ticks_left = ticks_left.saturating_sub(1);
+                    (tile_info.material, *ticks_left)
+                }
+                None => return,
+            },
+            None => return,
+        };
+
+        // Consume the tile when it burns out, explosives are left for the detonation
+        if ticks_left == 0 && self.materials.get(material).blast_radius.is_none() {
+            self.replace_tile(tile, None, temperature, view_update);
+            return;
+        }
+
+        // Spread to flammable neighbours
+        let random = self.random.tile(tile.global_position(), RandomStream::Fire);
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                let neighbour = tile.shifted(ivec2(dx, dy));
+                let chunk = match self.chunks.get_mut(&neighbour.chunk_pos) {
+                    Some(chunk) => chunk,
+                    None => continue,
+                };
+                if let Some(tile_info) = &chunk.tile_info[neighbour.index] {
+                    let neighbour_material = self.materials.get(tile_info.material);
+                    if chunk.layers.burning[neighbour.index].is_none()
+                        && random.with(neighbour.global_position()).next_f32()
+                            < neighbour_material.flammability
+                    {
+                        chunk.layers.burning[neighbour.index] = Some(neighbour_material.burn_time);
+                        chunk.queue_update(neighbour.index);
+                        if let Some(view_update) = view_update.get_mut(&neighbour.chunk_pos) {
+                            view_update[neighbour.index] = Some(chunk.tile(neighbour.index));
+                        }
+                    }
+                }
+            }
+        }
+
+        // Emit smoke into free space above
+        if let Some(smoke) = self.materials.get(material).smoke {
+            let above = tile.shifted(self.gravity.at(tile.global_position()).rotate(ivec2(0, 1)));
+            if self.chunks.contains_key(&above.chunk_pos)
+                && self.material_at(above).is_none()
+                && self
+                    .random
+                    .tile(tile.global_position(), RandomStream::Smoke)
+                    .next_f32()
+                    < SMOKE_CHANCE
+            {
+                self.replace_tile(above, Some(smoke), temperature, view_update);
+            }
+        }
+    }
+}
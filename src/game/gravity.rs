@@ -0,0 +1,250 @@
+use macroquad::prelude::{ivec2, IVec2, Vec2};
+use serde::Deserialize;
+use std::path::Path;
+
+use super::tile_move_direction::{DirectionError, TileMoveDirection};
+
+/// Neighbouring directions in counter-clockwise order, starting downwards
+const DIRECTIONS: [(i32, i32); 8] = [
+    (0, -1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+];
+
+#[derive(Debug)]
+pub enum GravityError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    InvalidDirection(DirectionError),
+}
+
+impl std::fmt::Display for GravityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "failed to read world: {}", error),
+            Self::Parse(error) => write!(f, "failed to parse world: {}", error),
+            Self::InvalidDirection(error) => write!(f, "invalid gravity: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for GravityError {}
+
+impl From<std::io::Error> for GravityError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<toml::de::Error> for GravityError {
+    fn from(error: toml::de::Error) -> Self {
+        Self::Parse(error)
+    }
+}
+
+impl From<DirectionError> for GravityError {
+    fn from(error: DirectionError) -> Self {
+        Self::InvalidDirection(error)
+    }
+}
+
+/// Direction tiles fall in, movement rules are described for gravity pointing down
+/// and rotated to match it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Gravity {
+    /// Number of eighth turns counter-clockwise from pointing down
+    rotation: usize,
+}
+
+impl Gravity {
+    pub fn new(direction: IVec2) -> Result<Self, DirectionError> {
+        let direction = TileMoveDirection::new(direction)?.direction();
+        Ok(Self {
+            rotation: direction_index(direction),
+        })
+    }
+
+    pub fn direction(&self) -> IVec2 {
+        self.rotate(ivec2(0, -1))
+    }
+
+    /// Rotates a direction described for gravity pointing down
+    pub fn rotate(&self, direction: IVec2) -> IVec2 {
+        let (x, y) = DIRECTIONS[(direction_index(direction) + self.rotation) % DIRECTIONS.len()];
+        ivec2(x, y)
+    }
+
+    /// Unit vector pointing along gravity
+    pub fn down(&self) -> Vec2 {
+        self.direction().as_f32().normalize()
+    }
+
+    /// Unit vector pointing right, seen with gravity pointing down
+    pub fn right(&self) -> Vec2 {
+        self.rotate(ivec2(1, 0)).as_f32().normalize()
+    }
+
+    /// How far a position is up against gravity, positions on a line
+    /// across gravity have the same height
+    pub fn height(&self, position: IVec2) -> i32 {
+        -position.dot(self.direction())
+    }
+
+    /// Identifies the line along gravity a position is on
+    pub fn line(&self, position: IVec2) -> i32 {
+        position.dot(self.rotate(ivec2(1, 0)))
+    }
+}
+
+fn direction_index(direction: IVec2) -> usize {
+    DIRECTIONS
+        .iter()
+        .position(|&(x, y)| ivec2(x, y) == direction)
+        .expect("direction must point to a neighbouring tile")
+}
+
+/// Box of tiles with its own gravity, the bounds are included
+#[derive(Clone, Debug)]
+struct GravityRegion {
+    min: IVec2,
+    max: IVec2,
+    gravity: Gravity,
+}
+
+/// Gravity of a world, regions override it inside them
+#[derive(Clone, Debug, Default)]
+pub struct GravityField {
+    gravity: Gravity,
+    regions: Vec<GravityRegion>,
+}
+
+#[derive(Deserialize)]
+struct RegionConfig {
+    min: [i32; 2],
+    max: [i32; 2],
+    gravity: [i32; 2],
+}
+
+fn default_gravity() -> [i32; 2] {
+    [0, -1]
+}
+
+#[derive(Deserialize)]
+struct WorldConfig {
+    #[serde(default = "default_gravity")]
+    gravity: [i32; 2],
+    #[serde(rename = "region", default)]
+    regions: Vec<RegionConfig>,
+}
+
+impl GravityField {
+    pub fn new(gravity: Gravity) -> Self {
+        Self {
+            gravity,
+            regions: Vec::new(),
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, GravityError> {
+        let source = std::fs::read_to_string(path)?;
+        Self::from_toml(&source)
+    }
+
+    pub fn from_toml(source: &str) -> Result<Self, GravityError> {
+        let config: WorldConfig = toml::from_str(source)?;
+        let [x, y] = config.gravity;
+        let mut field = Self::new(Gravity::new(ivec2(x, y))?);
+        for region in config.regions {
+            let ([min_x, min_y], [max_x, max_y]) = (region.min, region.max);
+            let [x, y] = region.gravity;
+            field = field.with_region(
+                ivec2(min_x, min_y),
+                ivec2(max_x, max_y),
+                Gravity::new(ivec2(x, y))?,
+            );
+        }
+        Ok(field)
+    }
+
+    /// Adds a region, later regions take precedence over earlier ones
+    pub fn with_region(mut self, min: IVec2, max: IVec2, gravity: Gravity) -> Self {
+        self.regions.push(GravityRegion {
+            min: min.min(max),
+            max: min.max(max),
+            gravity,
+        });
+        self
+    }
+
+    /// Gravity at a global tile position
+    pub fn at(&self, position: IVec2) -> Gravity {
+        self.regions
+            .iter()
+            .rev()
+            .find(|region| position.cmpge(region.min).all() && position.cmple(region.max).all())
+            .map_or(self.gravity, |region| region.gravity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotates_directions() {
+        let down = Gravity::default();
+        assert_eq!(down.direction(), ivec2(0, -1));
+        assert_eq!(down.rotate(ivec2(1, 1)), ivec2(1, 1));
+
+        let right = Gravity::new(ivec2(1, 0)).unwrap();
+        assert_eq!(right.rotate(ivec2(0, -1)), ivec2(1, 0));
+        assert_eq!(right.rotate(ivec2(0, 1)), ivec2(-1, 0));
+        assert_eq!(right.rotate(ivec2(1, 0)), ivec2(0, 1));
+        assert_eq!(right.rotate(ivec2(-1, -1)), ivec2(1, -1));
+
+        for &(x, y) in &DIRECTIONS {
+            let gravity = Gravity::new(ivec2(x, y)).unwrap();
+            assert_eq!(gravity.direction(), ivec2(x, y));
+            assert_eq!(gravity.rotate(ivec2(0, 1)), -ivec2(x, y));
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_gravity() {
+        assert!(Gravity::new(ivec2(0, 0)).is_err());
+        assert!(Gravity::new(ivec2(2, 0)).is_err());
+        assert!(matches!(
+            GravityField::from_toml("gravity = [0, 3]"),
+            Err(GravityError::InvalidDirection(_))
+        ));
+    }
+
+    #[test]
+    fn later_regions_take_precedence() {
+        let field = GravityField::from_toml(
+            r#"
+            gravity = [0, 1]
+
+            [[region]]
+            min = [0, 0]
+            max = [9, 9]
+            gravity = [1, 0]
+
+            [[region]]
+            min = [5, 5]
+            max = [9, 9]
+            gravity = [-1, 0]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(field.at(ivec2(-1, 0)).direction(), ivec2(0, 1));
+        assert_eq!(field.at(ivec2(0, 0)).direction(), ivec2(1, 0));
+        assert_eq!(field.at(ivec2(9, 9)).direction(), ivec2(-1, 0));
+        assert_eq!(field.at(ivec2(10, 9)).direction(), ivec2(0, 1));
+    }
+}
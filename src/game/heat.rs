@@ -0,0 +1,44 @@
+use rayon::prelude::*;
+
+use super::{calculator::ViewUpdates, World};
+
+impl World {
+    pub fn diffuse_heat(&mut self) {
+        // Calculate all chunks from the old temperatures, so the order doesn't matter
+        let temperatures = self
+            .chunks
+            .par_iter()
+            .map(|(&chunk_pos, chunk)| (chunk_pos, chunk.heat_flow(&self.chunks, &self.materials)))
+            .collect::<Vec<_>>();
+
+        for (chunk_pos, temperature) in temperatures {
+            self.chunks.get_mut(&chunk_pos).unwrap().layers.temperature = temperature;
+        }
+    }
+
+    pub fn apply_transitions(&mut self, view_update: &mut ViewUpdates) {
+        let materials = &self.materials;
+        let transitions = self
+            .chunks
+            .par_iter_mut()
+            .map(|(&chunk_pos, chunk)| (chunk_pos, chunk.apply_transitions(materials)))
+            .collect::<Vec<_>>();
+
+        for (chunk_pos, (changed, extra_updates)) in transitions {
+            // Show changed tiles
+            let chunk = &self.chunks[&chunk_pos];
+            if let Some(view_update) = view_update.get_mut(&chunk_pos) {
+                for index in changed {
+                    view_update[index] = Some(chunk.tile(index));
+                }
+            }
+
+            // Wake tiles in other chunks
+            for update_tile in extra_updates {
+                if let Some(chunk) = self.chunks.get_mut(&update_tile.chunk_pos) {
+                    chunk.queue_update(update_tile.index);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use macroquad::prelude::Vec2;
+
+use crate::constants::AMBIENT_TEMPERATURE;
+
+use super::{
+    chunk::{data_array, DataArray},
+    material::MaterialId,
+    tile::{BodyId, PlantId},
+    tile_move::HorizontalMove,
+};
+
+/// Registers the per-tile layers of a chunk, each layer gets an array in `TileLayers`,
+/// travelling layers also get a field in `LayerValues` and are moved, taken and set
+/// together with their tile, fixed layers belong to the position
+macro_rules! layers {
+    (
+        travelling {
+            $($(#[$travelling_meta:meta])* $travelling:ident: $travelling_type:ty = $travelling_default:expr,)*
+        }
+        fixed {
+            $($(#[$fixed_meta:meta])* $fixed:ident: $fixed_type:ty = $fixed_default:expr,)*
+        }
+    ) => {
+        /// Per-tile data of a chunk, one array per layer
+        #[derive(Clone, Debug)]
+        pub struct TileLayers {
+            $($(#[$travelling_meta])* pub $travelling: DataArray<$travelling_type>,)*
+            $($(#[$fixed_meta])* pub $fixed: DataArray<$fixed_type>,)*
+        }
+
+        /// Values of the travelling layers for a single tile
+        #[derive(Clone, Copy, Debug)]
+        pub struct LayerValues {
+            $($(#[$travelling_meta])* pub $travelling: $travelling_type,)*
+        }
+
+        impl Default for LayerValues {
+            /// Values of a free tile
+            fn default() -> Self {
+                Self {
+                    $($travelling: $travelling_default,)*
+                }
+            }
+        }
+
+        impl Default for TileLayers {
+            fn default() -> Self {
+                Self {
+                    $($travelling: data_array($travelling_default),)*
+                    $($fixed: data_array($fixed_default),)*
+                }
+            }
+        }
+
+        impl TileLayers {
+            pub fn get(&self, index: usize) -> LayerValues {
+                LayerValues {
+                    $($travelling: self.$travelling[index],)*
+                }
+            }
+
+            pub fn set(&mut self, index: usize, values: LayerValues) {
+                $(self.$travelling[index] = values.$travelling;)*
+            }
+        }
+    };
+}
+
+layers! {
+    travelling {
+        temperature: f32 = AMBIENT_TEMPERATURE,
+        /// Falling speed in tiles per tick
+        velocity: f32 = 0.0,
+        /// Ticks the tile has existed as its current material
+        age: u32 = 0,
+        /// Picks the tile's color from the palette of its material
+        shade: u8 = 0,
+        /// Side a spreading tile tries first
+        priority: HorizontalMove = HorizontalMove::Left,
+        /// The tile's last move was sideways
+        spreading: bool = false,
+        /// Velocity of a tile flung through the air, in tiles per tick,
+        /// flying tiles are moved by it instead of falling
+        flight: Option<Vec2> = None,
+        /// Ticks left until a burning tile is consumed
+        burning: Option<u32> = None,
+        /// Rigid body the tile is welded into
+        body: Option<BodyId> = None,
+        /// Ticks left until the tile can be charged again,
+        /// a freshly charged tile passes the charge on
+        charge: u8 = 0,
+        /// Plant of the seed the tile has grown from, a seed gets one when it starts growing
+        plant: Option<PlantId> = None,
+        /// The tile's plant has reached its size
+        grown: bool = false,
+        /// Number of tiles a porous tile has absorbed
+        saturation: u8 = 0,
+        /// Material dissolved in a liquid tile
+        solute: Option<MaterialId> = None,
+        /// Number of tiles of the solute dissolved in the tile
+        concentration: u8 = 0,
+    }
+    fixed {
+        /// Force the wind exerts on tiles, its sideways part makes light tiles drift
+        wind: Vec2 = Vec2::ZERO,
+        need_update: bool = false,
+        /// Tiles that may react with a neighbour, but haven't yet
+        reacting: bool = false,
+        cant_move: bool = false,
+    }
+}
+
+impl TileLayers {
+    /// Takes the values out of a tile, leaving the values of a free tile
+    pub fn take(&mut self, index: usize) -> LayerValues {
+        let values = self.get(index);
+        self.set(index, LayerValues::default());
+        values
+    }
+
+    /// Number of tiles the tile moves in one tick
+    pub fn speed(&self, index: usize) -> usize {
+        (self.velocity[index] as usize).max(1)
+    }
+}
@@ -0,0 +1,50 @@
+use macroquad::prelude::ivec2;
+use rayon::prelude::*;
+use std::collections::HashSet;
+
+use super::{calculator::ViewUpdates, chunk::MovingTile, random::RandomStream, tile::Tile, World};
+
+impl World {
+    pub fn step_life(&mut self, view_update: &mut ViewUpdates) {
+        let materials = &self.materials;
+        let chunks = &self.chunks;
+
+        // Only chunks next to ones with living tiles can change
+        let living = chunks
+            .par_iter()
+            .filter(|(_, chunk)| chunk.has_life(materials))
+            .map(|(&chunk_pos, _)| chunk_pos)
+            .collect::<HashSet<_>>();
+        if living.is_empty() {
+            return;
+        }
+
+        // Calculate all chunks from the current generation, so the order doesn't matter
+        let mut changes = chunks
+            .par_iter()
+            .filter(|(&chunk_pos, _)| {
+                (-1..=1).any(|dx| (-1..=1).any(|dy| living.contains(&(chunk_pos + ivec2(dx, dy)))))
+            })
+            .map(|(&chunk_pos, chunk)| (chunk_pos, chunk.life_step(chunks, materials)))
+            .collect::<Vec<_>>();
+        changes.sort_by_key(|(chunk_pos, _)| (chunk_pos.x, chunk_pos.y));
+
+        for (chunk_pos, changes) in changes {
+            for (index, material) in changes {
+                let tile = Tile { chunk_pos, index };
+                match material {
+                    Some(material) => {
+                        let born = MovingTile::spawn(
+                            material,
+                            &self.materials,
+                            self.random
+                                .tile(tile.global_position(), RandomStream::Shade),
+                        );
+                        self.place_tile(tile, Some(born), view_update);
+                    }
+                    None => self.place_tile(tile, None, view_update),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,851 @@
+use macroquad::prelude::{ivec2, Color, IVec2};
+use serde::Deserialize;
+use std::{collections::HashMap, path::Path};
+
+use crate::constants::AMBIENT_TEMPERATURE;
+
+use super::tile_move_direction::{DirectionError, TileMoveDirection};
+
+pub type MaterialId = usize;
+
+/// Reaction product that removes the tile
+const EMPTY_PRODUCT: &str = "empty";
+
+#[derive(Debug)]
+pub enum MaterialError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    DuplicateName(String),
+    UnknownMaterial(String),
+    InvalidTransition(String),
+    InvalidLifeRule(String),
+    InvalidHeatCapacity(String),
+    InvalidConductivity(String),
+    InvalidFriction(String),
+    InvalidDrag(String),
+    /// The chance of one of the material's rules, named by the second field
+    InvalidChance(String, &'static str),
+    InvalidEmitterDirection(String, DirectionError),
+}
+
+impl std::fmt::Display for MaterialError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "failed to read materials: {}", error),
+            Self::Parse(error) => write!(f, "failed to parse materials: {}", error),
+            Self::DuplicateName(name) => write!(f, "material {:?} is registered twice", name),
+            Self::UnknownMaterial(name) => write!(f, "material {:?} does not exist", name),
+            Self::InvalidTransition(name) => write!(
+                f,
+                "transition of material {:?} has no temperature threshold",
+                name
+            ),
+            Self::InvalidLifeRule(name) => write!(
+                f,
+                "life rule of material {:?} is not in the form \"B3/S23\"",
+                name
+            ),
+            Self::InvalidHeatCapacity(name) => {
+                write!(f, "heat capacity of material {:?} is not positive", name)
+            }
+            Self::InvalidConductivity(name) => write!(
+                f,
+                "conductivity of material {:?} is not between 0 and 1",
+                name
+            ),
+            Self::InvalidFriction(name) => {
+                write!(f, "friction of material {:?} is not between 0 and 1", name)
+            }
+            Self::InvalidDrag(name) => {
+                write!(f, "drag of material {:?} is not between 0 and 1", name)
+            }
+            Self::InvalidChance(name, rule) => write!(
+                f,
+                "{} chance of material {:?} is not between 0 and 1",
+                rule, name
+            ),
+            Self::InvalidEmitterDirection(name, error) => {
+                write!(f, "emitter of material {:?}: {}", name, error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MaterialError {}
+
+impl From<std::io::Error> for MaterialError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<toml::de::Error> for MaterialError {
+    fn from(error: toml::de::Error) -> Self {
+        Self::Parse(error)
+    }
+}
+
+/// Describes how tiles of a material move around
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MovementRule {
+    Static,
+    Powder,
+    Liquid,
+    Gas,
+    /// Falls together with the other tiles of its rigid body
+    Rigid,
+}
+
+impl MovementRule {
+    pub fn is_fluid(&self) -> bool {
+        matches!(self, Self::Liquid | Self::Gas)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct MaterialFlags {
+    /// Hidden materials are not bound to a key
+    pub hidden: bool,
+    /// Tiles of burning materials are placed already burning
+    pub burning: bool,
+    /// Drains delete any tile that moves into them
+    pub drain: bool,
+    /// Conductors pass charges on to their neighbours
+    pub conductor: bool,
+    /// Power sources charge their neighbours every tick
+    pub power: bool,
+}
+
+/// Life-like cellular automaton rule, tiles are born on empty tiles and survive
+/// depending on how many of the 8 neighbours are of the same material
+#[derive(Clone, Copy, Debug)]
+pub struct LifeRule {
+    pub birth: [bool; 9],
+    pub survival: [bool; 9],
+}
+
+impl LifeRule {
+    /// Parses a rule in the form "B3/S23"
+    pub fn parse(rule: &str) -> Option<Self> {
+        let mut life_rule = Self {
+            birth: [false; 9],
+            survival: [false; 9],
+        };
+        let (birth, survival) = rule.split_once('/')?;
+        for (part, prefix, counts) in [
+            (birth, 'B', &mut life_rule.birth),
+            (survival, 'S', &mut life_rule.survival),
+        ] {
+            let digits = part.strip_prefix(prefix)?;
+            for digit in digits.chars() {
+                *counts.get_mut(digit.to_digit(10)? as usize)? = true;
+            }
+        }
+        Some(life_rule)
+    }
+}
+
+/// Grows new tiles by consuming neighbouring tiles of another material
+#[derive(Clone, Copy, Debug)]
+pub struct Growth {
+    /// Material of the grown tiles, a growing tile of another material turns into it
+    pub into: MaterialId,
+    pub consumes: MaterialId,
+    /// Most tiles a plant grows to, counting its seed
+    pub size: u32,
+    /// Chance of growing in one tick
+    pub chance: f32,
+}
+
+/// Soaks up neighbouring tiles of a liquid, a saturated tile turns into another material
+#[derive(Clone, Copy, Debug)]
+pub struct Absorption {
+    pub absorbs: MaterialId,
+    /// Number of tiles absorbed until the tile is saturated
+    pub capacity: u8,
+    pub into: MaterialId,
+    /// Chance of absorbing a neighbour in one tick
+    pub chance: f32,
+}
+
+/// Dissolves into neighbouring tiles of a liquid
+#[derive(Clone, Copy, Debug)]
+pub struct Solubility {
+    pub solvent: MaterialId,
+    /// Most tiles dissolved in one tile of the solvent
+    pub limit: u8,
+    /// Chance of dissolving in one tick
+    pub chance: f32,
+}
+
+/// Spawns tiles next to the emitting tile
+#[derive(Clone, Copy, Debug)]
+pub struct Emitter {
+    pub material: MaterialId,
+    /// Where the tiles are spawned, relative to the emitter, described for gravity pointing down
+    pub direction: IVec2,
+    /// Chance of spawning a tile in one tick
+    pub rate: f32,
+}
+
+/// Turns a tile into another material when its temperature crosses a threshold
+#[derive(Clone, Copy, Debug)]
+pub struct Transition {
+    pub into: MaterialId,
+    pub below: Option<f32>,
+    pub above: Option<f32>,
+}
+
+impl Transition {
+    pub fn applies(&self, temperature: f32) -> bool {
+        self.below.is_none_or(|below| temperature < below)
+            && self.above.is_none_or(|above| temperature > above)
+    }
+}
+
+/// Two neighbouring tiles turning into other materials
+#[derive(Clone, Copy, Debug)]
+pub struct Reaction {
+    /// What the tiles turn into, in the order the reactants were given
+    pub products: [Option<MaterialId>; 2],
+    /// Chance of reacting in one tick
+    pub probability: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct Material {
+    pub name: String,
+    /// Colors tiles of the material are drawn in, each tile picks one by its shade
+    pub palette: Vec<Color>,
+    pub movement: MovementRule,
+    pub density: f32,
+    /// Chance of a resting tile not sliding diagonally in one tick, from 0 to 1
+    pub friction: f32,
+    /// Number of tiles a liquid or gas moves sideways in one tick
+    pub dispersion: usize,
+    /// How strongly wind pushes the tile, the chance of drifting in a wind of strength 1
+    pub drag: f32,
+    /// How much heat it takes to change the temperature, must be positive
+    pub heat_capacity: f32,
+    /// How easily heat flows to neighbouring tiles, from 0 to 1
+    pub conductivity: f32,
+    /// Temperature of newly placed tiles
+    pub temperature: f32,
+    pub transitions: Vec<Transition>,
+    /// Chance to catch fire from a burning neighbour in one tick
+    pub flammability: f32,
+    /// Ticks a burning tile lasts before it is consumed, explosives go off instead
+    pub burn_time: u32,
+    /// Material emitted above a burning tile
+    pub smoke: Option<MaterialId>,
+    /// Ticks a tile lasts before it decays
+    pub lifetime: Option<u32>,
+    /// Material a tile decays into, `None` removes it
+    pub decay: Option<MaterialId>,
+    pub emitter: Option<Emitter>,
+    /// Temperature a tile gains when it is charged
+    pub electric_heat: f32,
+    /// Material a tile turns into when it is charged
+    pub toggle: Option<MaterialId>,
+    pub growth: Option<Growth>,
+    pub life: Option<LifeRule>,
+    /// Explosive tiles detonate when they burn, destroying tiles within this radius
+    pub blast_radius: Option<f32>,
+    /// Strength of a blast a tile withstands, from 0 at the blast's edge to 1 at its center
+    pub blast_resistance: f32,
+    /// Material a solid tile destroyed by a blast turns into, `None` removes it
+    pub debris: Option<MaterialId>,
+    pub absorption: Option<Absorption>,
+    pub solubility: Option<Solubility>,
+    pub flags: MaterialFlags,
+}
+
+impl Material {
+    /// Checks whether tiles of this material take charges
+    pub fn is_electric(&self) -> bool {
+        self.flags.conductor || self.electric_heat != 0.0 || self.toggle.is_some()
+    }
+
+    /// Returns the material that a tile turns into at the given temperature
+    pub fn transition(&self, temperature: f32) -> Option<MaterialId> {
+        self.transitions
+            .iter()
+            .find(|transition| transition.applies(temperature))
+            .map(|transition| transition.into)
+    }
+}
+
+/// Material as it is described in a materials file
+#[derive(Deserialize)]
+struct MaterialConfig {
+    name: String,
+    color: [u8; 3],
+    #[serde(default)]
+    shades: Vec<[u8; 3]>,
+    movement: MovementRule,
+    #[serde(default)]
+    density: f32,
+    #[serde(default)]
+    friction: f32,
+    #[serde(default = "default_dispersion")]
+    dispersion: usize,
+    #[serde(default)]
+    drag: f32,
+    #[serde(default = "default_heat_capacity")]
+    heat_capacity: f32,
+    #[serde(default)]
+    conductivity: f32,
+    #[serde(default = "default_temperature")]
+    temperature: f32,
+    #[serde(default)]
+    transitions: Vec<TransitionConfig>,
+    #[serde(default)]
+    flammability: f32,
+    #[serde(default)]
+    burn_time: u32,
+    smoke: Option<String>,
+    lifetime: Option<u32>,
+    #[serde(default = "default_decay")]
+    decay: String,
+    emitter: Option<EmitterConfig>,
+    #[serde(default)]
+    electric_heat: f32,
+    toggle: Option<String>,
+    growth: Option<GrowthConfig>,
+    life: Option<String>,
+    blast_radius: Option<f32>,
+    #[serde(default)]
+    blast_resistance: f32,
+    debris: Option<String>,
+    absorption: Option<AbsorptionConfig>,
+    solubility: Option<SolubilityConfig>,
+    #[serde(default)]
+    flags: MaterialFlags,
+}
+
+/// Transition as it is described in a materials file, refers to materials by name
+#[derive(Deserialize)]
+struct TransitionConfig {
+    into: String,
+    below: Option<f32>,
+    above: Option<f32>,
+}
+
+/// Growth as it is described in a materials file, refers to materials by name
+#[derive(Deserialize)]
+struct GrowthConfig {
+    into: String,
+    consumes: String,
+    size: u32,
+    #[serde(default = "default_probability")]
+    chance: f32,
+}
+
+/// Absorption as it is described in a materials file, refers to materials by name
+#[derive(Deserialize)]
+struct AbsorptionConfig {
+    absorbs: String,
+    capacity: u8,
+    into: String,
+    #[serde(default = "default_probability")]
+    chance: f32,
+}
+
+/// Solubility as it is described in a materials file, refers to the solvent by name
+#[derive(Deserialize)]
+struct SolubilityConfig {
+    solvent: String,
+    limit: u8,
+    #[serde(default = "default_probability")]
+    chance: f32,
+}
+
+/// Emitter as it is described in a materials file, refers to the material by name
+#[derive(Deserialize)]
+struct EmitterConfig {
+    material: String,
+    #[serde(default = "default_direction")]
+    direction: [i32; 2],
+    #[serde(default = "default_probability")]
+    rate: f32,
+}
+
+fn default_direction() -> [i32; 2] {
+    [0, -1]
+}
+
+fn default_dispersion() -> usize {
+    1
+}
+
+fn default_decay() -> String {
+    EMPTY_PRODUCT.to_string()
+}
+
+fn default_heat_capacity() -> f32 {
+    1.0
+}
+
+fn default_temperature() -> f32 {
+    AMBIENT_TEMPERATURE
+}
+
+/// Reaction as it is described in a materials file, refers to materials by name
+#[derive(Deserialize)]
+struct ReactionConfig {
+    reactants: [String; 2],
+    products: [String; 2],
+    #[serde(default = "default_probability")]
+    probability: f32,
+}
+
+fn default_probability() -> f32 {
+    1.0
+}
+
+#[derive(Deserialize)]
+struct MaterialsConfig {
+    #[serde(rename = "material", default)]
+    materials: Vec<MaterialConfig>,
+    #[serde(rename = "reaction", default)]
+    reactions: Vec<ReactionConfig>,
+}
+
+#[derive(Default)]
+pub struct MaterialRegistry {
+    materials: Vec<Material>,
+    ids: HashMap<String, MaterialId>,
+    reactions: HashMap<(MaterialId, MaterialId), Reaction>,
+}
+
+impl MaterialRegistry {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, MaterialError> {
+        let source = std::fs::read_to_string(path)?;
+        Self::from_toml(&source)
+    }
+
+    pub fn from_toml(source: &str) -> Result<Self, MaterialError> {
+        let config: MaterialsConfig = toml::from_str(source)?;
+
+        let mut registry = Self::default();
+        let mut references = Vec::with_capacity(config.materials.len());
+        for material in config.materials {
+            let palette = std::iter::once(material.color)
+                .chain(material.shades.iter().copied())
+                .map(|[r, g, b]| Color::from_rgba(r, g, b, 255))
+                .collect();
+            let life = match &material.life {
+                Some(rule) => Some(
+                    LifeRule::parse(rule)
+                        .ok_or_else(|| MaterialError::InvalidLifeRule(material.name.clone()))?,
+                ),
+                None => None,
+            };
+            if material.heat_capacity <= 0.0 || material.heat_capacity.is_nan() {
+                return Err(MaterialError::InvalidHeatCapacity(material.name));
+            }
+            if !(0.0..=1.0).contains(&material.conductivity) {
+                return Err(MaterialError::InvalidConductivity(material.name));
+            }
+            if !(0.0..=1.0).contains(&material.friction) {
+                return Err(MaterialError::InvalidFriction(material.name));
+            }
+            if !(0.0..=1.0).contains(&material.drag) {
+                return Err(MaterialError::InvalidDrag(material.name));
+            }
+            for (rule, chance) in [
+                (
+                    "growth",
+                    material.growth.as_ref().map(|config| config.chance),
+                ),
+                (
+                    "absorption",
+                    material.absorption.as_ref().map(|config| config.chance),
+                ),
+                (
+                    "solubility",
+                    material.solubility.as_ref().map(|config| config.chance),
+                ),
+            ] {
+                if chance.is_some_and(|chance| !(0.0..=1.0).contains(&chance)) {
+                    return Err(MaterialError::InvalidChance(material.name, rule));
+                }
+            }
+            let id = registry.register(Material {
+                name: material.name,
+                palette,
+                movement: material.movement,
+                density: material.density,
+                friction: material.friction,
+                dispersion: material.dispersion,
+                drag: material.drag,
+                heat_capacity: material.heat_capacity,
+                conductivity: material.conductivity,
+                temperature: material.temperature,
+                transitions: Vec::new(),
+                flammability: material.flammability,
+                burn_time: material.burn_time,
+                smoke: None,
+                lifetime: material.lifetime,
+                decay: None,
+                emitter: None,
+                electric_heat: material.electric_heat,
+                toggle: None,
+                growth: None,
+                life,
+                blast_radius: material.blast_radius,
+                blast_resistance: material.blast_resistance,
+                debris: None,
+                absorption: None,
+                solubility: None,
+                flags: material.flags,
+            })?;
+            references.push((
+                id,
+                material.transitions,
+                material.smoke,
+                material.decay,
+                material.emitter,
+                material.toggle,
+                material.growth,
+                material.debris,
+                material.absorption,
+                material.solubility,
+            ));
+        }
+
+        // Materials may refer to materials listed later in the file
+        for (
+            id,
+            transitions,
+            smoke,
+            decay,
+            emitter,
+            toggle,
+            growth,
+            debris,
+            absorption,
+            solubility,
+        ) in references
+        {
+            if let Some(smoke) = smoke {
+                registry.materials[id].smoke = Some(registry.id(&smoke)?);
+            }
+            if let Some(toggle) = toggle {
+                registry.materials[id].toggle = Some(registry.id(&toggle)?);
+            }
+            if let Some(debris) = debris {
+                registry.materials[id].debris = Some(registry.id(&debris)?);
+            }
+            if let Some(config) = growth {
+                registry.materials[id].growth = Some(Growth {
+                    into: registry.id(&config.into)?,
+                    consumes: registry.id(&config.consumes)?,
+                    size: config.size,
+                    chance: config.chance,
+                });
+            }
+            if let Some(config) = absorption {
+                registry.materials[id].absorption = Some(Absorption {
+                    absorbs: registry.id(&config.absorbs)?,
+                    capacity: config.capacity,
+                    into: registry.id(&config.into)?,
+                    chance: config.chance,
+                });
+            }
+            if let Some(config) = solubility {
+                registry.materials[id].solubility = Some(Solubility {
+                    solvent: registry.id(&config.solvent)?,
+                    limit: config.limit,
+                    chance: config.chance,
+                });
+            }
+            registry.materials[id].decay = registry.product(&decay)?;
+            if let Some(config) = emitter {
+                let [x, y] = config.direction;
+                let direction = TileMoveDirection::new(ivec2(x, y)).map_err(|error| {
+                    MaterialError::InvalidEmitterDirection(registry.get(id).name.clone(), error)
+                })?;
+                registry.materials[id].emitter = Some(Emitter {
+                    material: registry.id(&config.material)?,
+                    direction: direction.direction(),
+                    rate: config.rate,
+                });
+            }
+            for config in transitions {
+                let transition = Transition {
+                    into: registry.id(&config.into)?,
+                    below: config.below,
+                    above: config.above,
+                };
+                if transition.below.is_none() && transition.above.is_none() {
+                    return Err(MaterialError::InvalidTransition(
+                        registry.get(id).name.clone(),
+                    ));
+                }
+                registry.materials[id].transitions.push(transition);
+            }
+        }
+
+        for config in config.reactions {
+            let [first, second] = config.reactants;
+            let [first_product, second_product] = config.products;
+            let reaction = Reaction {
+                products: [
+                    registry.product(&first_product)?,
+                    registry.product(&second_product)?,
+                ],
+                probability: config.probability,
+            };
+            registry.register_reaction(registry.id(&first)?, registry.id(&second)?, reaction);
+        }
+        Ok(registry)
+    }
+
+    pub fn register(&mut self, material: Material) -> Result<MaterialId, MaterialError> {
+        if self.ids.contains_key(&material.name) {
+            return Err(MaterialError::DuplicateName(material.name));
+        }
+
+        let id = self.materials.len();
+        self.ids.insert(material.name.clone(), id);
+        self.materials.push(material);
+        Ok(id)
+    }
+
+    pub fn register_reaction(&mut self, first: MaterialId, second: MaterialId, reaction: Reaction) {
+        // Register the reaction both ways, so the order of the neighbours doesn't matter
+        let [first_product, second_product] = reaction.products;
+        self.reactions.insert(
+            (second, first),
+            Reaction {
+                products: [second_product, first_product],
+                probability: reaction.probability,
+            },
+        );
+        self.reactions.insert((first, second), reaction);
+    }
+
+    pub fn id(&self, name: &str) -> Result<MaterialId, MaterialError> {
+        self.ids
+            .get(name)
+            .copied()
+            .ok_or_else(|| MaterialError::UnknownMaterial(name.to_string()))
+    }
+
+    fn product(&self, name: &str) -> Result<Option<MaterialId>, MaterialError> {
+        if name == EMPTY_PRODUCT {
+            Ok(None)
+        } else {
+            self.id(name).map(Some)
+        }
+    }
+
+    /// Returns the reaction between a tile and its neighbour
+    pub fn reaction(&self, material: MaterialId, neighbour: MaterialId) -> Option<&Reaction> {
+        self.reactions.get(&(material, neighbour))
+    }
+
+    pub fn get(&self, id: MaterialId) -> &Material {
+        &self.materials[id]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (MaterialId, &Material)> {
+        self.materials.iter().enumerate()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_references_to_later_materials() {
+        let registry = MaterialRegistry::from_toml(
+            r#"
+            [[material]]
+            name = "ice"
+            color = [170, 220, 250]
+            movement = "static"
+            transitions = [{ above = 0.0, into = "water" }]
+
+            [[material]]
+            name = "water"
+            color = [0, 121, 241]
+            movement = "liquid"
+            density = 1.0
+
+            [[reaction]]
+            reactants = ["ice", "water"]
+            products = ["water", "empty"]
+            "#,
+        )
+        .unwrap();
+
+        let ice = registry.id("ice").unwrap();
+        let water = registry.id("water").unwrap();
+        assert_eq!((ice, water), (0, 1));
+        assert_eq!(registry.get(ice).transitions[0].into, water);
+        assert_eq!(registry.get(water).movement, MovementRule::Liquid);
+        assert_eq!(registry.get(water).dispersion, 1);
+        assert_eq!(
+            registry.reaction(water, ice).unwrap().products,
+            [None, Some(water)]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_materials() {
+        let duplicate = r#"
+            [[material]]
+            name = "sand"
+            color = [253, 249, 0]
+            movement = "powder"
+
+            [[material]]
+            name = "sand"
+            color = [253, 249, 0]
+            movement = "powder"
+            "#;
+        assert!(matches!(
+            MaterialRegistry::from_toml(duplicate),
+            Err(MaterialError::DuplicateName(_))
+        ));
+
+        let unknown = r#"
+            [[material]]
+            name = "wood"
+            color = [130, 82, 45]
+            movement = "static"
+            smoke = "soot"
+            "#;
+        assert!(matches!(
+            MaterialRegistry::from_toml(unknown),
+            Err(MaterialError::UnknownMaterial(_))
+        ));
+
+        let no_threshold = r#"
+            [[material]]
+            name = "ice"
+            color = [170, 220, 250]
+            movement = "static"
+            transitions = [{ into = "ice" }]
+            "#;
+        assert!(matches!(
+            MaterialRegistry::from_toml(no_threshold),
+            Err(MaterialError::InvalidTransition(_))
+        ));
+
+        let life = r#"
+            [[material]]
+            name = "life"
+            color = [230, 230, 230]
+            movement = "static"
+            life = "23/3"
+            "#;
+        assert!(matches!(
+            MaterialRegistry::from_toml(life),
+            Err(MaterialError::InvalidLifeRule(_))
+        ));
+
+        let heat_capacity = r#"
+            [[material]]
+            name = "stone"
+            color = [130, 130, 130]
+            movement = "static"
+            heat_capacity = 0.0
+            "#;
+        assert!(matches!(
+            MaterialRegistry::from_toml(heat_capacity),
+            Err(MaterialError::InvalidHeatCapacity(_))
+        ));
+
+        let conductivity = r#"
+            [[material]]
+            name = "stone"
+            color = [130, 130, 130]
+            movement = "static"
+            conductivity = 1.5
+            "#;
+        assert!(matches!(
+            MaterialRegistry::from_toml(conductivity),
+            Err(MaterialError::InvalidConductivity(_))
+        ));
+
+        let friction = r#"
+            [[material]]
+            name = "sand"
+            color = [253, 249, 0]
+            movement = "powder"
+            friction = -0.5
+            "#;
+        assert!(matches!(
+            MaterialRegistry::from_toml(friction),
+            Err(MaterialError::InvalidFriction(_))
+        ));
+
+        let drag = r#"
+            [[material]]
+            name = "smoke"
+            color = [90, 90, 90]
+            movement = "gas"
+            drag = 2.0
+            "#;
+        assert!(matches!(
+            MaterialRegistry::from_toml(drag),
+            Err(MaterialError::InvalidDrag(_))
+        ));
+
+        let chance = r#"
+            [[material]]
+            name = "salt"
+            color = [240, 240, 240]
+            movement = "powder"
+            solubility = { solvent = "salt", limit = 2, chance = 1.5 }
+            "#;
+        assert!(matches!(
+            MaterialRegistry::from_toml(chance),
+            Err(MaterialError::InvalidChance(_, "solubility"))
+        ));
+
+        for direction in ["[0, 0]", "[0, 50]"] {
+            let emitter = format!(
+                r#"
+                [[material]]
+                name = "faucet"
+                color = [200, 200, 200]
+                movement = "static"
+                emitter = {{ material = "faucet", direction = {} }}
+                "#,
+                direction
+            );
+            assert!(matches!(
+                MaterialRegistry::from_toml(&emitter),
+                Err(MaterialError::InvalidEmitterDirection(..))
+            ));
+        }
+    }
+
+    #[test]
+    fn loads_materials_file() {
+        let registry = MaterialRegistry::load(crate::constants::MATERIALS_PATH).unwrap();
+        assert!(registry.id("sand").is_ok());
+    }
+
+    #[test]
+    fn parses_life_rules() {
+        let conway = LifeRule::parse("B3/S23").unwrap();
+        let counts = |counts: [bool; 9]| (0..9).filter(|&n| counts[n]).collect::<Vec<_>>();
+        assert_eq!(counts(conway.birth), vec![3]);
+        assert_eq!(counts(conway.survival), vec![2, 3]);
+
+        let empty = LifeRule::parse("B/S").unwrap();
+        assert_eq!(counts(empty.birth), vec![]);
+
+        for invalid in &["B3S23", "S23/B3", "B9/S23", "B3/S2x", ""] {
+            assert!(LifeRule::parse(invalid).is_none(), "{}", invalid);
+        }
+    }
+}
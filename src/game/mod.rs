@@ -1,69 +1,122 @@
 use macroquad::prelude::{
-    is_key_pressed, is_mouse_button_down, ivec2, uvec2, IVec2, KeyCode, MouseButton,
+    is_key_down, is_key_pressed, is_mouse_button_down, is_mouse_button_pressed, ivec2, IVec2,
+    KeyCode, MouseButton, Vec2,
 };
 use std::collections::HashMap;
 
 use crate::{
-    constants::{CHUNK_SIZE_X, CHUNK_SIZE_Y},
-    game::{chunk::tile_position_to_index, tile::TileInfo},
+    constants::{
+        CHUNK_SIZE, CHUNK_SIZE_X, CHUNK_SIZE_Y, MIN_WIND, TOOL_BLAST_RADIUS, TOOL_WIND_FORCE,
+        TOOL_WIND_RADIUS, WIND_DECAY,
+    },
     update_view::UpdateView,
 };
 
+mod absorption;
+mod body;
 mod calculator;
-mod chunk;
+pub mod chunk;
+mod decay;
+mod dispersion;
+mod electricity;
+mod explosion;
+mod fall;
+mod fire;
+pub mod gravity;
+mod heat;
+mod layers;
+mod life;
+pub mod material;
+mod plant;
+mod pressure;
+mod random;
+mod reaction;
 mod renderer;
+mod solution;
 mod tick;
 pub mod tile;
 mod tile_move;
 mod tile_move_direction;
+mod wind;
 
-use chunk::{tile_index_to_position, Chunk};
+use chunk::{tile_index_to_position, Chunk, MovingTile};
+use gravity::GravityField;
+use material::{MaterialId, MaterialRegistry, MovementRule};
+use random::{RandomStream, WorldRandom};
 use renderer::Renderer;
 
-use self::{tile::Tile, tile_move::HorizontalMove};
+use self::{
+    calculator::ViewUpdates,
+    tile::{BodyId, Tile},
+};
+
+/// Keys that select materials, in the order materials are registered
+const MATERIAL_KEYS: [KeyCode; 30] = [
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::Key0,
+    KeyCode::Q,
+    KeyCode::W,
+    KeyCode::E,
+    KeyCode::R,
+    KeyCode::T,
+    KeyCode::Y,
+    KeyCode::U,
+    KeyCode::I,
+    KeyCode::O,
+    KeyCode::A,
+    KeyCode::S,
+    KeyCode::D,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::X,
+    KeyCode::C,
+];
 
 pub struct Game {
-    chunks: HashMap<IVec2, Chunk>,
+    world: World,
     renderer: Renderer,
+    selected_material: Option<MaterialId>,
+    /// Body that rigid tiles placed with the current stroke are welded into
+    welding: BodyId,
+    /// Where the mouse was in the last frame, while wind is painted
+    wind_brush: Option<Vec2>,
+}
+
+/// Tiles and the rules they follow, everything a tick changes
+pub struct World {
+    chunks: HashMap<IVec2, Chunk>,
+    materials: MaterialRegistry,
+    gravity: GravityField,
+    random: WorldRandom,
     view_update: UpdateView,
-    selected_tile: Option<TileInfo>,
+    /// Blasts set off by hand, with their radius, they go off during the next tick
+    blasts: Vec<(Tile, f32)>,
+    /// Number of tiles of every plant, indexed by its id
+    plants: Vec<u32>,
+    next_body: BodyId,
 }
 
 impl Game {
-    pub fn new() -> Self {
-        let mut game = Self {
-            chunks: {
-                let mut chunks = HashMap::new();
-                const CHUNKS: i32 = 1;
-                for x in -CHUNKS..=CHUNKS {
-                    for y in 0..=CHUNKS * 2 {
-                        let pos = ivec2(x, y);
-                        chunks.insert(pos, Chunk::empty(pos));
-                    }
-                }
-                chunks
-            },
+    pub fn new(materials: MaterialRegistry, gravity: GravityField, seed: u64) -> Self {
+        Self {
+            world: World::new(materials, gravity, seed),
             renderer: Renderer::new(),
-            view_update: UpdateView::default(),
-            selected_tile: None,
-        };
-
-        game.view_update.update_view(
-            game.chunks
-                .iter()
-                .map(|(&chunk_pos, chunk)| {
-                    chunk.tiles().map(move |(index, tile)| {
-                        (
-                            tile_index_to_position(index)
-                                + chunk_pos * ivec2(CHUNK_SIZE_X as i32, CHUNK_SIZE_Y as i32),
-                            tile.clone(),
-                        )
-                    })
-                })
-                .flatten(),
-        );
-
-        game
+            selected_material: None,
+            welding: 0,
+            wind_brush: None,
+        }
     }
 
     pub fn update(&mut self, delta_time: f32) {
@@ -72,28 +125,83 @@ impl Game {
     }
 
     pub fn fixed_update(&mut self, _delta_time: f32) {
-        self.tick();
+        // Painted wind dies down over time
+        self.world.animate_wind(|_, wind| {
+            let wind = wind * WIND_DECAY;
+            if wind.length() < MIN_WIND {
+                Vec2::ZERO
+            } else {
+                wind
+            }
+        });
+        self.world.tick();
     }
 
     pub fn draw(&mut self) {
-        self.renderer.draw(std::mem::take(&mut self.view_update));
+        self.renderer.draw(
+            std::mem::take(&mut self.world.view_update),
+            &self.world.materials,
+        );
     }
 
     fn handle_input(&mut self) {
-        // Select tile
-        if is_key_pressed(KeyCode::Key1) {
-            self.selected_tile = Some(TileInfo::Barrier);
-        } else if is_key_pressed(KeyCode::Key2) {
-            self.selected_tile = Some(TileInfo::Sand);
-        } else if is_key_pressed(KeyCode::Key3) {
-            self.selected_tile = Some(TileInfo::Water {
-                priority: HorizontalMove::Left,
-            });
+        let world = &mut self.world;
+
+        // Select material
+        if let Some((material, _)) = world
+            .materials
+            .iter()
+            .filter(|(_, material)| !material.flags.hidden)
+            .zip(MATERIAL_KEYS.iter())
+            .find(|(_, &key)| is_key_pressed(key))
+        {
+            self.selected_material = Some(material.0);
+        }
+
+        let mouse_pos = self.renderer.mouse_world_pos();
+        let mouse_tile = Tile::from_global_position(ivec2(
+            mouse_pos.x.floor() as i32,
+            mouse_pos.y.floor() as i32,
+        ));
+
+        // Set off a blast
+        if is_key_pressed(KeyCode::Z) {
+            world.blasts.push((mouse_tile, TOOL_BLAST_RADIUS));
+        }
+
+        // Paint wind in the direction the mouse is dragged, or calm it
+        if is_key_down(KeyCode::V) {
+            if let Some(last_pos) = self.wind_brush.replace(mouse_pos) {
+                if mouse_pos != last_pos {
+                    let force = (mouse_pos - last_pos).normalize() * TOOL_WIND_FORCE;
+                    world.paint_wind(mouse_tile.global_position(), TOOL_WIND_RADIUS, force);
+                }
+            }
+        } else {
+            self.wind_brush = None;
+        }
+        if is_key_down(KeyCode::B) {
+            world.paint_wind(mouse_tile.global_position(), TOOL_WIND_RADIUS, Vec2::ZERO);
+        }
+
+        // Every stroke welds a new rigid body
+        if is_mouse_button_pressed(MouseButton::Left) {
+            self.welding = world.new_body();
         }
 
         // Place or delete tile
+        let welding = self.welding;
         let selected_tile = if is_mouse_button_down(MouseButton::Left) {
-            Some(self.selected_tile.clone())
+            Some(self.selected_material.map(|material| {
+                let random = world
+                    .random
+                    .tile(mouse_tile.global_position(), RandomStream::Shade);
+                let mut tile = MovingTile::spawn(material, &world.materials, random);
+                if world.materials.get(material).movement == MovementRule::Rigid {
+                    tile.layers.body = Some(welding);
+                }
+                tile
+            }))
         } else if is_mouse_button_down(MouseButton::Right) {
             Some(None)
         } else {
@@ -102,49 +210,166 @@ impl Game {
 
         // Do thing
         if let Some(selected_tile) = selected_tile {
-            self.set_tile(self.mouse_over_tile(), selected_tile);
+            world.set_tile(mouse_tile, selected_tile);
         }
     }
+}
 
-    fn set_tile(&mut self, tile: Tile, tile_info: Option<TileInfo>) {
+impl World {
+    pub fn new(materials: MaterialRegistry, gravity: GravityField, seed: u64) -> Self {
+        let mut world = Self {
+            chunks: {
+                let mut chunks = HashMap::new();
+                const CHUNKS: i32 = 1;
+                for x in -CHUNKS..=CHUNKS {
+                    for y in 0..=CHUNKS * 2 {
+                        let pos = ivec2(x, y);
+                        chunks.insert(pos, Chunk::empty(pos));
+                    }
+                }
+                chunks
+            },
+            materials,
+            gravity,
+            random: WorldRandom::new(seed),
+            view_update: UpdateView::default(),
+            blasts: Vec::new(),
+            plants: Vec::new(),
+            next_body: 0,
+        };
+        world
+            .view_update
+            .update_view(world.chunks.iter().flat_map(|(&chunk_pos, chunk)| {
+                (0..CHUNK_SIZE).map(move |index| {
+                    (
+                        tile_index_to_position(index)
+                            + chunk_pos * ivec2(CHUNK_SIZE_X as i32, CHUNK_SIZE_Y as i32),
+                        chunk.tile(index),
+                    )
+                })
+            }));
+
+        world
+    }
+
+    fn set_tile(&mut self, tile: Tile, placed: Option<MovingTile>) {
         if let Some(chunk) = self.chunks.get_mut(&tile.chunk_pos) {
-            for extra_update in chunk.set_tile(tile.index, tile_info.clone()) {
+            for extra_update in chunk.set_tile(tile.index, placed.clone()) {
                 if let Some(chunk) = self.chunks.get_mut(&extra_update.chunk_pos) {
                     chunk.queue_update(extra_update.index);
                 }
             }
-            self.view_update
-                .update_tile(tile.global_position(), tile_info);
+            self.view_update.update_tile(tile.global_position(), placed);
         }
     }
 
-    fn mouse_over_tile(&self) -> Tile {
-        let mouse_world_pos = self.renderer.mouse_world_pos();
-        let tile_pos = ivec2(
-            mouse_world_pos.x.floor() as i32,
-            mouse_world_pos.y.floor() as i32,
-        );
+    fn material_at(&self, tile: Tile) -> Option<MaterialId> {
+        self.chunks
+            .get(&tile.chunk_pos)
+            .and_then(|chunk| chunk.tile_info[tile.index].as_ref())
+            .map(|tile_info| tile_info.material)
+    }
 
-        let chunk_size = ivec2(CHUNK_SIZE_X as i32, CHUNK_SIZE_Y as i32);
-        let mut chunk_pos = tile_pos / chunk_size;
-        let mut tile_pos = tile_pos - chunk_pos * chunk_size;
+    fn replace_tile(
+        &mut self,
+        tile: Tile,
+        material: Option<MaterialId>,
+        temperature: f32,
+        view_update: &mut ViewUpdates,
+    ) {
+        let random = self
+            .random
+            .tile(tile.global_position(), RandomStream::Shade);
+        let chunk = self.chunks.get_mut(&tile.chunk_pos).unwrap();
+        let extra_updates = chunk.replace_tile(tile.index, material, &self.materials, random);
+        if material.is_some() {
+            chunk.layers.temperature[tile.index] = temperature;
+        }
+        if let Some(view_update) = view_update.get_mut(&tile.chunk_pos) {
+            view_update[tile.index] = Some(chunk.tile(tile.index));
+        }
 
-        if tile_pos.x < 0 {
-            chunk_pos += ivec2(-1, 0);
-            tile_pos.x += CHUNK_SIZE_X as i32;
+        for update_tile in extra_updates {
+            if let Some(chunk) = self.chunks.get_mut(&update_tile.chunk_pos) {
+                chunk.queue_update(update_tile.index);
+            }
         }
+    }
 
-        if tile_pos.y < 0 {
-            chunk_pos += ivec2(0, -1);
-            tile_pos.y += CHUNK_SIZE_Y as i32;
+    /// Checks whether a tile exists in the world and is empty
+    fn is_free(&self, tile: Tile) -> bool {
+        self.chunks.contains_key(&tile.chunk_pos) && self.material_at(tile).is_none()
+    }
+
+    /// Moves a tile outside of the calculation, the target has to be free
+    fn move_tile(&mut self, from: Tile, to: Tile, view_update: &mut ViewUpdates) {
+        let moving = self.chunks[&from.chunk_pos].tile(from.index);
+        self.place_tile(from, None, view_update);
+        self.place_tile(to, moving, view_update);
+    }
+
+    /// Puts a tile into the world together with the data that moves with it
+    fn place_tile(
+        &mut self,
+        tile: Tile,
+        placed: Option<MovingTile>,
+        view_update: &mut ViewUpdates,
+    ) {
+        let chunk = self.chunks.get_mut(&tile.chunk_pos).unwrap();
+        if let Some(view_update) = view_update.get_mut(&tile.chunk_pos) {
+            view_update[tile.index] = Some(placed.clone());
         }
+        let extra_updates = chunk.set_tile(tile.index, placed);
 
-        let tile_position = uvec2(tile_pos.x as u32, tile_pos.y as u32);
+        for update_tile in extra_updates {
+            if let Some(chunk) = self.chunks.get_mut(&update_tile.chunk_pos) {
+                chunk.queue_update(update_tile.index);
+            }
+        }
+    }
+}
 
-        let tile_index = tile_position_to_index(tile_position);
-        Tile {
-            chunk_pos,
-            index: tile_index,
+#[cfg(test)]
+impl World {
+    /// Fills a rectangle with tiles of a material, as if they were placed by hand
+    fn fill(&mut self, material: &str, min: IVec2, max: IVec2) {
+        let material = self.materials.id(material).unwrap();
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                let position = ivec2(x, y);
+                let random = self.random.tile(position, RandomStream::Shade);
+                let placed = MovingTile::spawn(material, &self.materials, random);
+                self.set_tile(Tile::from_global_position(position), Some(placed));
+            }
         }
     }
+
+    fn place(&mut self, material: &str, position: IVec2) {
+        self.fill(material, position, position);
+    }
+
+    /// Positions of the tiles of a material
+    fn find(&self, material: &str) -> Vec<IVec2> {
+        let material = self.materials.id(material).unwrap();
+        let mut positions = self
+            .chunks
+            .values()
+            .flat_map(|chunk| {
+                chunk.tiles().filter_map(move |(index, tile_info)| {
+                    tile_info
+                        .as_ref()
+                        .filter(|tile_info| tile_info.material == material)
+                        .map(|_| {
+                            Tile {
+                                chunk_pos: chunk.chunk_pos,
+                                index,
+                            }
+                            .global_position()
+                        })
+                })
+            })
+            .collect::<Vec<_>>();
+        positions.sort_by_key(|position| (position.x, position.y));
+        positions
+    }
 }
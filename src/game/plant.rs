@@ -0,0 +1,153 @@
+use macroquad::prelude::ivec2;
+use rayon::prelude::*;
+
+use crate::constants::AMBIENT_TEMPERATURE;
+
+use super::{calculator::ViewUpdates, chunk::MovingTile, random::RandomStream, tile::Tile, World};
+
+/// Directions tiles grow in, described for gravity pointing down,
+/// growing upwards is twice as likely as the others
+const GROWTH_DIRECTIONS: [(i32, i32); 6] = [(0, 1), (0, 1), (-1, 1), (1, 1), (-1, 0), (1, 0)];
+
+impl World {
+    pub fn grow(&mut self, view_update: &mut ViewUpdates) {
+        // Find growing tiles in parallel
+        let materials = &self.materials;
+        let chunks = &self.chunks;
+        let mut candidates = chunks
+            .par_iter()
+            .map(|(&chunk_pos, chunk)| (chunk_pos, chunk.growth_candidates(chunks, materials)))
+            .collect::<Vec<_>>();
+        candidates.sort_by_key(|(chunk_pos, _)| (chunk_pos.x, chunk_pos.y));
+
+        for (chunk_pos, candidates) in candidates {
+            for (index, consumed) in candidates {
+                self.grow_tile(Tile { chunk_pos, index }, consumed, view_update);
+            }
+        }
+    }
+
+    /// Grows a new tile next to a tile, consuming the tile it grows into
+    /// or else the neighbour it has been found touching
+    fn grow_tile(&mut self, tile: Tile, consumed: Tile, view_update: &mut ViewUpdates) {
+        let material = match self.material_at(tile) {
+            Some(material) => material,
+            None => return,
+        };
+        let growth = match self.materials.get(material).growth {
+            Some(growth) => growth,
+            None => return,
+        };
+
+        // Another tile might have consumed the neighbour already
+        if self.material_at(consumed) != Some(growth.consumes) {
+            return;
+        }
+
+        // A seed starts a new plant, a plant stops growing once it has reached its size
+        let chunk = self.chunks.get_mut(&tile.chunk_pos).unwrap();
+        let plant = match chunk.layers.plant[tile.index] {
+            Some(plant) => plant,
+            None => {
+                self.plants.push(1);
+                let plant = self.plants.len() - 1;
+                chunk.layers.plant[tile.index] = Some(plant);
+                plant
+            }
+        };
+        if self.plants[plant] >= growth.size {
+            chunk.layers.grown[tile.index] = true;
+            return;
+        }
+
+        let mut random = self
+            .random
+            .tile(tile.global_position(), RandomStream::Growth);
+        if random.next_f32() >= growth.chance {
+            return;
+        }
+
+        let (x, y) =
+            GROWTH_DIRECTIONS[(random.next_u64() % GROWTH_DIRECTIONS.len() as u64) as usize];
+        let target = tile.shifted(self.gravity.at(tile.global_position()).rotate(ivec2(x, y)));
+        let consumed = if self.is_free(target) {
+            consumed
+        } else if self.material_at(target) == Some(growth.consumes) {
+            target
+        } else {
+            return;
+        };
+
+        let chunk = &self.chunks[&tile.chunk_pos];
+        let temperature = chunk.layers.temperature[tile.index];
+        let mut grown = MovingTile::spawn(
+            growth.into,
+            &self.materials,
+            self.random
+                .tile(target.global_position(), RandomStream::Shade),
+        );
+        grown.layers.temperature = temperature;
+        self.plants[plant] += 1;
+        grown.layers.plant = Some(plant);
+        grown.layers.grown = self.plants[plant] >= growth.size;
+
+        if consumed != target {
+            self.replace_tile(consumed, None, AMBIENT_TEMPERATURE, view_update);
+        }
+        self.place_tile(target, Some(grown), view_update);
+
+        // Seeds turn into what they grow once they have started growing
+        if material != growth.into {
+            self.replace_tile(tile, Some(growth.into), temperature, view_update);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{gravity::GravityField, material::MaterialRegistry};
+
+    const MATERIALS: &str = r#"
+        [[material]]
+        name = "barrier"
+        color = [255, 255, 255]
+        movement = "static"
+
+        [[material]]
+        name = "water"
+        color = [0, 121, 241]
+        movement = "liquid"
+        density = 1.0
+
+        [[material]]
+        name = "seed"
+        color = [150, 120, 50]
+        movement = "static"
+        growth = { into = "plant", consumes = "water", size = 12 }
+
+        [[material]]
+        name = "plant"
+        color = [40, 170, 50]
+        movement = "static"
+        growth = { into = "plant", consumes = "water", size = 12 }
+    "#;
+
+    #[test]
+    fn plants_stop_growing_at_their_size() {
+        let materials = MaterialRegistry::from_toml(MATERIALS).unwrap();
+        let mut world = World::new(materials, GravityField::default(), 0);
+        // A seed at the bottom of a pool with far more water than the plant needs
+        world.fill("barrier", ivec2(0, 0), ivec2(30, 0));
+        world.fill("barrier", ivec2(0, 1), ivec2(0, 30));
+        world.fill("barrier", ivec2(30, 1), ivec2(30, 30));
+        world.fill("water", ivec2(1, 1), ivec2(29, 20));
+        world.place("seed", ivec2(15, 1));
+
+        for _ in 0..100 {
+            world.tick();
+        }
+        assert_eq!(world.find("seed").len(), 0);
+        assert_eq!(world.find("plant").len(), 12);
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use macroquad::prelude::ivec2;
+use rayon::prelude::*;
+
+use crate::constants::PRESSURE_MOVES;
+
+use super::{calculator::ViewUpdates, tile::Tile, World};
+
+impl World {
+    /// Moves liquid from the surface of a body to free tiles next to it
+    /// that are lower, so levels in connected vessels equalize
+    pub fn apply_pressure(&mut self, view_update: &mut ViewUpdates) {
+        let materials = &self.materials;
+        // Liquid tiles by global position, with their material and whether they are falling
+        let liquid_tiles = self
+            .chunks
+            .par_iter()
+            .flat_map(|(&chunk_pos, chunk)| {
+                chunk
+                    .liquid_tiles(materials)
+                    .into_par_iter()
+                    .map(move |index| {
+                        let tile = Tile { chunk_pos, index };
+                        let tile_info = chunk.tile_info[index].as_ref().unwrap();
+                        (
+                            tile.global_position(),
+                            (
+                                tile_info.material,
+                                chunk.layers.velocity[index] > 0.0
+                                    || chunk.layers.flight[index].is_some(),
+                            ),
+                        )
+                    })
+            })
+            .collect::<HashMap<_, _>>();
+
+        let mut positions = liquid_tiles.keys().copied().collect::<Vec<_>>();
+        positions.sort_by_key(|position| (position.x, position.y));
+
+        let mut visited = HashSet::new();
+        for start in positions {
+            if !visited.insert(start) {
+                continue;
+            }
+
+            // Find the body of connected tiles of the same liquid,
+            // it is pressed on by the gravity where it is first found
+            let material = liquid_tiles[&start].0;
+            let gravity = self.gravity.at(start);
+            let up = gravity.rotate(ivec2(0, 1));
+            let mut body = vec![start];
+            let mut queue = VecDeque::from(vec![start]);
+            while let Some(position) = queue.pop_front() {
+                for shift in &[ivec2(1, 0), ivec2(-1, 0), ivec2(0, 1), ivec2(0, -1)] {
+                    let neighbour = position + *shift;
+                    if matches!(liquid_tiles.get(&neighbour), Some(&(m, _)) if m == material)
+                        && visited.insert(neighbour)
+                    {
+                        body.push(neighbour);
+                        queue.push_back(neighbour);
+                    }
+                }
+            }
+
+            // Falling tiles aren't pressed on by the rest of the body
+            let mut surface = Vec::new();
+            let mut outlets = Vec::new();
+            for &position in &body {
+                if liquid_tiles[&position].1 {
+                    continue;
+                }
+                if self.is_free(Tile::from_global_position(position + up)) {
+                    surface.push(position);
+                }
+                for shift in &[ivec2(1, 0), ivec2(-1, 0), ivec2(0, 1), ivec2(0, -1)] {
+                    let outlet = position + *shift;
+                    if self.is_free(Tile::from_global_position(outlet)) {
+                        outlets.push(outlet);
+                    }
+                }
+            }
+            surface.sort_by_key(|&position| (-gravity.height(position), position.x, position.y));
+            outlets.sort_by_key(|&position| (gravity.height(position), position.x, position.y));
+            outlets.dedup();
+
+            // Push the highest tiles out of the lowest outlets,
+            // a difference of one tile counts as level
+            for (from, to) in surface
+                .into_iter()
+                .zip(outlets)
+                .take(PRESSURE_MOVES)
+                .take_while(|&(from, to)| gravity.height(from) > gravity.height(to) + 1)
+            {
+                // Another body may have already filled the outlet
+                let to = Tile::from_global_position(to);
+                if !self.is_free(to) {
+                    continue;
+                }
+                self.move_tile(Tile::from_global_position(from), to, view_update);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use macroquad::prelude::ivec2;
+
+    use super::*;
+    use crate::{
+        constants::MATERIALS_PATH,
+        game::{gravity::GravityField, material::MaterialRegistry},
+    };
+
+    #[test]
+    fn bodies_sharing_an_outlet_keep_their_tiles() {
+        let materials = MaterialRegistry::load(MATERIALS_PATH).unwrap();
+        let mut world = World::new(materials, GravityField::default(), 0);
+        // Columns of water and oil on both sides of the same free tile
+        world.fill("barrier", ivec2(8, 0), ivec2(12, 0));
+        world.fill("barrier", ivec2(8, 1), ivec2(8, 10));
+        world.fill("barrier", ivec2(12, 1), ivec2(12, 10));
+        world.fill("water", ivec2(9, 1), ivec2(9, 6));
+        world.fill("oil", ivec2(11, 1), ivec2(11, 6));
+
+        world.apply_pressure(&mut HashMap::new());
+        assert_eq!(world.find("water").len(), 6);
+        assert_eq!(world.find("oil").len(), 6);
+    }
+}
@@ -0,0 +1,126 @@
+use macroquad::prelude::IVec2;
+
+/// Separates random numbers used for different purposes on the same tile
+#[derive(Clone, Copy, Debug)]
+pub enum RandomStream {
+    Movement,
+    Reaction,
+    Fire,
+    Smoke,
+    Emission,
+    Growth,
+    Absorption,
+    Solution,
+    Shade,
+}
+
+/// Source of random numbers for a world, they only depend on the seed,
+/// the tick and the tile's position, so the order tiles are calculated
+/// in doesn't change the results
+#[derive(Clone, Copy, Debug)]
+pub struct WorldRandom {
+    seed: u64,
+    tick: u64,
+}
+
+impl WorldRandom {
+    pub fn new(seed: u64) -> Self {
+        Self { seed, tick: 0 }
+    }
+
+    pub fn next_tick(&mut self) {
+        self.tick += 1;
+    }
+
+    pub fn tile(&self, position: IVec2, stream: RandomStream) -> TileRandom {
+        TileRandom {
+            state: mix(self.seed ^ mix(self.tick ^ mix(stream as u64))),
+        }
+        .with(position)
+    }
+}
+
+/// Random numbers for one tile in one tick
+#[derive(Clone, Copy, Debug)]
+pub struct TileRandom {
+    state: u64,
+}
+
+impl TileRandom {
+    /// Makes the numbers depend on another position, e.g. of a neighbour
+    pub fn with(self, position: IVec2) -> Self {
+        let position = position.x as u32 as u64 | (position.y as u32 as u64) << 32;
+        Self {
+            state: mix(self.state ^ mix(position)),
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        // SplitMix64
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        mix(self.state)
+    }
+
+    /// Returns a number in range 0..1
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    pub fn next_bool(&mut self) -> bool {
+        self.next_u64() & 1 == 1
+    }
+}
+
+fn mix(mut value: u64) -> u64 {
+    value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    value ^ (value >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use macroquad::prelude::ivec2;
+
+    fn numbers(random: WorldRandom, position: IVec2, stream: RandomStream) -> Vec<u64> {
+        let mut random = random.tile(position, stream);
+        (0..4).map(|_| random.next_u64()).collect()
+    }
+
+    #[test]
+    fn numbers_depend_on_seed_tick_position_and_stream() {
+        let random = WorldRandom::new(7);
+        let position = ivec2(3, -4);
+        let expected = numbers(random, position, RandomStream::Movement);
+        assert_eq!(numbers(random, position, RandomStream::Movement), expected);
+        assert_eq!(
+            numbers(WorldRandom::new(7), position, RandomStream::Movement),
+            expected
+        );
+
+        let mut next_tick = random;
+        next_tick.next_tick();
+        assert_ne!(
+            numbers(next_tick, position, RandomStream::Movement),
+            expected
+        );
+        assert_ne!(
+            numbers(WorldRandom::new(8), position, RandomStream::Movement),
+            expected
+        );
+        assert_ne!(
+            numbers(random, ivec2(-4, 3), RandomStream::Movement),
+            expected
+        );
+        assert_ne!(numbers(random, position, RandomStream::Fire), expected);
+    }
+
+    #[test]
+    fn floats_are_in_unit_range() {
+        let mut random = WorldRandom::new(1).tile(ivec2(0, 0), RandomStream::Movement);
+        let floats = (0..1000).map(|_| random.next_f32()).collect::<Vec<_>>();
+        assert!(floats.iter().all(|float| (0.0..1.0).contains(float)));
+        let mean = floats.iter().sum::<f32>() / floats.len() as f32;
+        assert!((mean - 0.5).abs() < 0.05, "mean {}", mean);
+    }
+}
@@ -0,0 +1,88 @@
+use rayon::prelude::*;
+use std::collections::HashSet;
+
+use super::{calculator::ViewUpdates, random::RandomStream, tile::Tile, World};
+
+impl World {
+    pub fn react(&mut self, view_update: &mut ViewUpdates) {
+        // Find reacting neighbours in parallel
+        let materials = &self.materials;
+        let chunks = &self.chunks;
+        let mut candidates = chunks
+            .par_iter()
+            .map(|(&chunk_pos, chunk)| (chunk_pos, chunk.reaction_candidates(chunks, materials)))
+            .collect::<Vec<_>>();
+        candidates.sort_by_key(|(chunk_pos, _)| (chunk_pos.x, chunk_pos.y));
+
+        for chunk in self.chunks.values_mut() {
+            chunk
+                .layers
+                .reacting
+                .iter_mut()
+                .for_each(|reacting| *reacting = false);
+        }
+
+        // Every tile reacts at most once per tick
+        let mut reacted = HashSet::new();
+        for (chunk_pos, candidates) in candidates {
+            for (index, neighbour) in candidates {
+                let tile = Tile { chunk_pos, index };
+                if reacted.contains(&tile) || reacted.contains(&neighbour) {
+                    continue;
+                }
+
+                let reaction = match (self.material_at(tile), self.material_at(neighbour)) {
+                    (Some(material), Some(neighbour_material)) => {
+                        match self.materials.reaction(material, neighbour_material) {
+                            Some(reaction) => *reaction,
+                            None => continue,
+                        }
+                    }
+                    _ => continue,
+                };
+
+                let roll = self
+                    .random
+                    .tile(tile.global_position(), RandomStream::Reaction)
+                    .with(neighbour.global_position())
+                    .next_f32();
+                if roll >= reaction.probability {
+                    // Try again next tick
+                    for tile in &[tile, neighbour] {
+                        self.chunks
+                            .get_mut(&tile.chunk_pos)
+                            .unwrap()
+                            .layers
+                            .reacting[tile.index] = true;
+                    }
+                    continue;
+                }
+
+                reacted.insert(tile);
+                reacted.insert(neighbour);
+
+                // Products share the heat of the reactants
+                let temperature = self.mixed_temperature(tile, neighbour);
+                let [product, neighbour_product] = reaction.products;
+                self.replace_tile(tile, product, temperature, view_update);
+                self.replace_tile(neighbour, neighbour_product, temperature, view_update);
+            }
+        }
+    }
+
+    fn mixed_temperature(&self, tile: Tile, neighbour: Tile) -> f32 {
+        let (heat, heat_capacity) =
+            [tile, neighbour]
+                .iter()
+                .fold((0.0, 0.0), |(heat, heat_capacity), tile| {
+                    let chunk = &self.chunks[&tile.chunk_pos];
+                    let material = self.material_at(*tile).unwrap();
+                    let tile_heat_capacity = self.materials.get(material).heat_capacity;
+                    (
+                        heat + chunk.layers.temperature[tile.index] * tile_heat_capacity,
+                        heat_capacity + tile_heat_capacity,
+                    )
+                });
+        heat / heat_capacity
+    }
+}
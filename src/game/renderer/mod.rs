@@ -2,7 +2,7 @@ use macroquad::{
     camera::{set_camera, Camera2D},
     prelude::{
         draw_rectangle_lines, draw_texture, ivec2, mouse_position, screen_height, screen_width,
-        vec2, Color, FilterMode, IVec2, Image, Texture2D, Vec2, BLACK, BLUE, WHITE, YELLOW,
+        vec2, Color, FilterMode, IVec2, Image, Texture2D, Vec2, BLACK, WHITE,
     },
 };
 
@@ -11,7 +11,7 @@ use crate::{
     update_view::UpdateView,
 };
 
-use super::tile::TileInfo;
+use super::{chunk::MovingTile, material::MaterialRegistry};
 
 pub struct Renderer {
     game_camera: Camera2D,
@@ -45,13 +45,13 @@ impl Renderer {
 
     pub fn update(&mut self, _delta_time: f32) {}
 
-    pub fn draw(&mut self, view: UpdateView) {
+    pub fn draw(&mut self, view: UpdateView, materials: &MaterialRegistry) {
         set_camera(&self.game_camera);
-        self.draw_game(view);
+        self.draw_game(view, materials);
         self.draw_chunks();
     }
 
-    fn draw_game(&mut self, view: UpdateView) {
+    fn draw_game(&mut self, view: UpdateView, materials: &MaterialRegistry) {
         // let offset = self.game_camera.world_to_screen(vec2(0.0, 0.0));
         // let offset = ivec2(offset.x as i32, offset.y as i32);
         let offset = ivec2(self.image.width as i32 / 2, 0);
@@ -64,8 +64,8 @@ impl Renderer {
             {
                 match tile {
                     None => self.image.set_pixel(pos.x as u32, pos.y as u32, BLACK),
-                    Some(tile_info) => {
-                        let color = tile_color(tile_info);
+                    Some(tile) => {
+                        let color = tile_color(tile, materials);
                         self.image.set_pixel(pos.x as u32, pos.y as u32, color);
                     }
                 }
@@ -98,10 +98,26 @@ impl Renderer {
     }
 }
 
-fn tile_color(tile_info: TileInfo) -> Color {
-    match tile_info {
-        TileInfo::Barrier => WHITE,
-        TileInfo::Sand => YELLOW,
-        TileInfo::Water { .. } => BLUE,
-    }
+/// Color that burning tiles are tinted with
+const FIRE_COLOR: Color = Color::new(1.0, 0.4, 0.0, 1.0);
+/// Color that charged tiles are tinted with
+const CHARGE_COLOR: Color = Color::new(1.0, 1.0, 0.6, 1.0);
+
+fn tile_color(tile: MovingTile, materials: &MaterialRegistry) -> Color {
+    let tile_info = &tile.tile_info;
+    let palette = &materials.get(tile_info.material).palette;
+    let color = palette[tile.layers.shade as usize % palette.len()];
+    let tint = if tile.is_burning() {
+        FIRE_COLOR
+    } else if tile.is_charged() {
+        CHARGE_COLOR
+    } else {
+        return color;
+    };
+    Color::new(
+        (color.r + tint.r) / 2.0,
+        (color.g + tint.g) / 2.0,
+        (color.b + tint.b) / 2.0,
+        1.0,
+    )
 }
@@ -0,0 +1,235 @@
+use rayon::prelude::*;
+use std::collections::{HashSet, VecDeque};
+
+use macroquad::prelude::ivec2;
+
+use crate::constants::AMBIENT_TEMPERATURE;
+
+use super::{calculator::ViewUpdates, chunk::MovingTile, random::RandomStream, tile::Tile, World};
+
+/// Where the solute is deposited around an evaporating tile, downwards first,
+/// described for gravity pointing down
+const DEPOSIT_DIRECTIONS: [(i32, i32); 8] = [
+    (0, -1),
+    (-1, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (1, 1),
+    (0, 1),
+];
+
+impl World {
+    /// Dissolves soluble tiles in neighbouring tiles of their solvent,
+    /// the solute is stored in the solvent tile and moves along with it
+    pub fn dissolve(&mut self, view_update: &mut ViewUpdates) {
+        // Find soluble tiles next to a solvent in parallel
+        let materials = &self.materials;
+        let chunks = &self.chunks;
+        let mut candidates = chunks
+            .par_iter()
+            .flat_map(|(_, chunk)| chunk.solution_candidates(chunks, materials))
+            .collect::<Vec<_>>();
+        candidates.sort_by_key(|(tile, solvent)| {
+            let (position, solvent_position) = (tile.global_position(), solvent.global_position());
+            (
+                position.x,
+                position.y,
+                solvent_position.x,
+                solvent_position.y,
+            )
+        });
+        candidates.dedup();
+
+        // Every tile dissolves or takes up a solute at most once per tick
+        let mut dissolved = HashSet::new();
+        for (tile, solvent) in candidates {
+            if dissolved.contains(&tile) || dissolved.contains(&solvent) {
+                continue;
+            }
+
+            let (material, solubility) = match (self.material_at(tile), self.material_at(solvent)) {
+                (Some(material), Some(solvent_material)) => {
+                    match self.materials.get(material).solubility {
+                        Some(solubility) if solubility.solvent == solvent_material => {
+                            (material, solubility)
+                        }
+                        _ => continue,
+                    }
+                }
+                _ => continue,
+            };
+
+            // A tile holds one solute up to its limit
+            let layers = &self.chunks[&solvent.chunk_pos].layers;
+            if layers.solute[solvent.index].is_some_and(|solute| solute != material)
+                || layers.concentration[solvent.index] >= solubility.limit
+            {
+                continue;
+            }
+
+            let roll = self
+                .random
+                .tile(tile.global_position(), RandomStream::Solution)
+                .with(solvent.global_position())
+                .next_f32();
+            if roll >= solubility.chance {
+                // Try again next tick
+                self.chunks
+                    .get_mut(&tile.chunk_pos)
+                    .unwrap()
+                    .queue_update(tile.index);
+                continue;
+            }
+
+            dissolved.insert(tile);
+            dissolved.insert(solvent);
+            self.replace_tile(tile, None, AMBIENT_TEMPERATURE, view_update);
+
+            let chunk = self.chunks.get_mut(&solvent.chunk_pos).unwrap();
+            chunk.layers.solute[solvent.index] = Some(material);
+            chunk.layers.concentration[solvent.index] += 1;
+            chunk.queue_update(solvent.index);
+        }
+    }
+
+    /// Takes the solute out of evaporating tiles, it's deposited in free tiles around
+    /// or passed on to neighbouring tiles of the solvent, whatever doesn't fit anywhere is lost
+    pub fn precipitate(&mut self, view_update: &mut ViewUpdates) {
+        let materials = &self.materials;
+        let mut candidates = self
+            .chunks
+            .par_iter()
+            .flat_map(|(&chunk_pos, chunk)| {
+                chunk
+                    .precipitation_candidates(materials)
+                    .into_par_iter()
+                    .map(move |index| Tile { chunk_pos, index })
+            })
+            .collect::<Vec<_>>();
+        candidates.sort_by_key(|tile| {
+            let position = tile.global_position();
+            (position.x, position.y)
+        });
+
+        // Neighbours that take up the solute might be evaporating as well,
+        // it isn't passed back to the tiles that have given it away
+        let mut candidates = VecDeque::from(candidates);
+        let mut evaporated = HashSet::new();
+        while let Some(tile) = candidates.pop_front() {
+            let chunk = self.chunks.get_mut(&tile.chunk_pos).unwrap();
+            if !chunk.precipitates(tile.index, &self.materials) {
+                continue;
+            }
+            evaporated.insert(tile);
+            let temperature = chunk.layers.temperature[tile.index];
+            let solute = chunk.layers.solute[tile.index].take().unwrap();
+            let mut remaining = std::mem::take(&mut chunk.layers.concentration[tile.index]);
+
+            let solubility = match self.materials.get(solute).solubility {
+                Some(solubility) => solubility,
+                None => continue,
+            };
+            let gravity = self.gravity.at(tile.global_position());
+            for &(x, y) in &DEPOSIT_DIRECTIONS {
+                if remaining == 0 {
+                    break;
+                }
+
+                let neighbour = tile.shifted(gravity.rotate(ivec2(x, y)));
+                if self.is_free(neighbour) {
+                    let mut deposit = MovingTile::spawn(
+                        solute,
+                        &self.materials,
+                        self.random
+                            .tile(neighbour.global_position(), RandomStream::Shade),
+                    );
+                    deposit.layers.temperature = temperature;
+                    self.place_tile(neighbour, Some(deposit), view_update);
+                    remaining -= 1;
+                } else if evaporated.contains(&neighbour) {
+                    continue;
+                } else if let Some(chunk) = self.chunks.get_mut(&neighbour.chunk_pos) {
+                    let layers = &mut chunk.layers;
+                    let index = neighbour.index;
+                    if chunk.tile_info[index]
+                        .as_ref()
+                        .is_some_and(|tile_info| tile_info.material == solubility.solvent)
+                        && layers.solute[index].unwrap_or(solute) == solute
+                        && layers.concentration[index] < solubility.limit
+                    {
+                        let taken = remaining.min(solubility.limit - layers.concentration[index]);
+                        layers.solute[index] = Some(solute);
+                        layers.concentration[index] += taken;
+                        remaining -= taken;
+                        candidates.push_back(neighbour);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use macroquad::prelude::IVec2;
+
+    use super::*;
+    use crate::{
+        constants::MATERIALS_PATH,
+        game::{
+            gravity::{Gravity, GravityField},
+            material::MaterialRegistry,
+        },
+    };
+
+    /// Evaporates a tile of salty water, returns where the steam and the salt end up
+    fn evaporate(gravity: IVec2) -> (Vec<IVec2>, Vec<IVec2>) {
+        let materials = MaterialRegistry::load(MATERIALS_PATH).unwrap();
+        let water = materials.id("water").unwrap();
+        let steam = materials.id("steam").unwrap();
+        let salt = materials.id("salt").unwrap();
+        let gravity = GravityField::new(Gravity::new(gravity).unwrap());
+        let mut world = World::new(materials, gravity, 0);
+
+        let tile = Tile::from_global_position(ivec2(10, 10));
+        let mut placed = MovingTile::new(water, &world.materials);
+        placed.layers.solute = Some(salt);
+        placed.layers.concentration = 2;
+        world.set_tile(tile, Some(placed));
+        world
+            .chunks
+            .get_mut(&tile.chunk_pos)
+            .unwrap()
+            .layers
+            .temperature[tile.index] = 150.0;
+
+        let mut view_update = Default::default();
+        world.precipitate(&mut view_update);
+        world.apply_transitions(&mut view_update);
+
+        let find = |material| {
+            (9..=11)
+                .flat_map(|y| (9..=11).map(move |x| ivec2(x, y)))
+                .filter(|&position| {
+                    world.material_at(Tile::from_global_position(position)) == Some(material)
+                })
+                .collect::<Vec<_>>()
+        };
+        (find(steam), find(salt))
+    }
+
+    #[test]
+    fn evaporating_water_leaves_its_solute_next_to_it() {
+        assert_eq!(
+            evaporate(ivec2(0, -1)),
+            (vec![ivec2(10, 10)], vec![ivec2(9, 9), ivec2(10, 9)])
+        );
+        // The solute is deposited along the local gravity
+        assert_eq!(
+            evaporate(ivec2(1, 0)),
+            (vec![ivec2(10, 10)], vec![ivec2(11, 9), ivec2(11, 10)])
+        );
+    }
+}
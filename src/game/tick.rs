@@ -1,13 +1,74 @@
 use super::{
     calculator::{Calculator, ViewUpdates},
+    gravity::GravityField,
+    material::MaterialRegistry,
+    random::WorldRandom,
     tile::Tile,
-    Game,
+    World,
 };
 
-impl Game {
+/// Information shared by all chunks during a tick
+#[derive(Clone, Copy)]
+pub struct TickContext<'a> {
+    pub materials: &'a MaterialRegistry,
+    pub gravity: &'a GravityField,
+    pub random: WorldRandom,
+}
+
+impl World {
     pub fn tick(&mut self) {
+        self.random.next_tick();
+
+        // Weld rigid tiles into bodies, split the ones that have been cut apart
+        self.split_bodies();
+
         // Calculate and perform movement
-        let view_update = self.perform_tick();
+        let mut view_update = self.perform_tick();
+
+        // Let fast tiles fall further
+        self.fall(&mut view_update);
+
+        // Move tiles flung by blasts
+        self.fly(&mut view_update);
+
+        // Let spreading tiles flow further sideways
+        self.disperse(&mut view_update);
+
+        // Let liquid levels in connected vessels equalize
+        self.apply_pressure(&mut view_update);
+
+        // Let heat flow between tiles and change their materials,
+        // evaporating liquid leaves its solute behind
+        self.diffuse_heat();
+        self.precipitate(&mut view_update);
+        self.apply_transitions(&mut view_update);
+
+        // Let neighbouring tiles react with each other
+        self.react(&mut view_update);
+
+        // Let porous tiles soak up liquid
+        self.absorb(&mut view_update);
+
+        // Dissolve soluble tiles in neighbouring liquid
+        self.dissolve(&mut view_update);
+
+        // Spread fire and consume burning tiles
+        self.burn(&mut view_update);
+
+        // Detonate burning explosives and blasts set off by hand
+        self.detonate(&mut view_update);
+
+        // Pass charges along conductors and power the charged tiles
+        self.conduct(&mut view_update);
+
+        // Let plants grow by consuming the water they touch
+        self.grow(&mut view_update);
+
+        // Let tiles with a life rule be born and die
+        self.step_life(&mut view_update);
+
+        // Remove or transform tiles that have reached the end of their lifetime
+        self.decay(&mut view_update);
 
         // Update view
         for (chunk_pos, update_view) in view_update {
@@ -22,7 +83,7 @@ impl Game {
         }
     }
 
-    fn perform_tick(&mut self) -> ViewUpdates {
+    pub(super) fn perform_tick(&mut self) -> ViewUpdates {
         // Calculate chunks mostly in parallel
         let mut calculator = Calculator::new(self.chunks.keys().copied());
         calculator.tick(
@@ -30,6 +91,98 @@ impl Game {
                 .iter_mut()
                 .map(|(&pos, chunk)| (pos, chunk))
                 .collect(),
+            TickContext {
+                materials: &self.materials,
+                gravity: &self.gravity,
+                random: self.random,
+            },
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use macroquad::prelude::{ivec2, IVec2};
+
+    use super::*;
+    use crate::{
+        constants::MATERIALS_PATH,
+        game::{chunk::MovingTile, random::RandomStream},
+    };
+
+    /// Runs a scene with most kinds of tiles, returns every tile with its temperature
+    fn run(seed: u64, threads: usize) -> Vec<(IVec2, usize, u8, u32)> {
+        let materials = MaterialRegistry::load(MATERIALS_PATH).unwrap();
+        let mut world = World::new(materials, GravityField::default(), seed);
+        world.fill("barrier", ivec2(-50, 0), ivec2(99, 0));
+        world.fill("sand", ivec2(-30, 60), ivec2(-10, 90));
+        world.fill("water", ivec2(-5, 40), ivec2(20, 55));
+        world.fill("oil", ivec2(25, 30), ivec2(35, 45));
+        world.fill("wood", ivec2(45, 1), ivec2(55, 10));
+        world.fill("fire", ivec2(50, 11), ivec2(50, 11));
+        world.fill("salt", ivec2(0, 70), ivec2(5, 75));
+        world.fill("seed", ivec2(-40, 20), ivec2(-38, 20));
+        world.fill("faucet", ivec2(70, 60), ivec2(70, 60));
+        world.fill("lava", ivec2(80, 20), ivec2(85, 25));
+        world.fill("gunpowder", ivec2(60, 1), ivec2(62, 3));
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        pool.install(|| {
+            for _ in 0..120 {
+                world.tick();
+            }
+        });
+
+        let mut tiles = world
+            .chunks
+            .values()
+            .flat_map(|chunk| {
+                chunk.tiles().filter_map(move |(index, tile_info)| {
+                    let tile = Tile {
+                        chunk_pos: chunk.chunk_pos,
+                        index,
+                    };
+                    tile_info.as_ref().map(|tile_info| {
+                        (
+                            tile.global_position(),
+                            tile_info.material,
+                            chunk.layers.shade[index],
+                            chunk.layers.temperature[index].to_bits(),
+                        )
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+        tiles.sort_by_key(|&(position, ..)| (position.x, position.y));
+        tiles
+    }
+
+    #[test]
+    fn same_seed_gives_same_world() {
+        let tiles = run(42, 4);
+        assert_eq!(run(42, 4), tiles);
+        assert_eq!(run(42, 1), tiles);
+        assert_ne!(run(43, 4), tiles);
+    }
+
+    #[test]
+    fn tiles_appearing_in_free_places_get_a_shade() {
+        let materials = MaterialRegistry::load(MATERIALS_PATH).unwrap();
+        let smoke = materials.id("smoke").unwrap();
+        let mut world = World::new(materials, GravityField::default(), 42);
+        for x in 0..8 {
+            let tile = Tile::from_global_position(ivec2(x, 10));
+            world.replace_tile(tile, Some(smoke), 20.0, &mut Default::default());
+
+            let random = world
+                .random
+                .tile(tile.global_position(), RandomStream::Shade);
+            let spawned = MovingTile::spawn(smoke, &world.materials, random);
+            let chunk = &world.chunks[&tile.chunk_pos];
+            assert_eq!(chunk.layers.shade[tile.index], spawned.layers.shade);
+        }
+    }
+}
@@ -1,13 +1,10 @@
-use macroquad::prelude::{ivec2, IVec2};
+use macroquad::prelude::{ivec2, uvec2, IVec2};
 
-use crate::{
-    constants::{CHUNK_SIZE_X, CHUNK_SIZE_Y},
-    game::tile_move::TileMove,
-};
+use crate::constants::{CHUNK_SIZE_X, CHUNK_SIZE_Y};
 
 use super::{
-    chunk::tile_index_to_position, tile_move::HorizontalMove,
-    tile_move_direction::TileMoveDirection,
+    chunk::{tile_index_to_position, tile_position_to_index},
+    material::{MaterialId, MaterialRegistry},
 };
 
 #[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
@@ -17,46 +14,60 @@ pub struct Tile {
 }
 
 impl Tile {
+    pub fn from_global_position(position: IVec2) -> Self {
+        let chunk_size = ivec2(CHUNK_SIZE_X as i32, CHUNK_SIZE_Y as i32);
+        let chunk_pos = ivec2(
+            position.x.div_euclid(chunk_size.x),
+            position.y.div_euclid(chunk_size.y),
+        );
+        let tile_pos = position - chunk_pos * chunk_size;
+        Self {
+            chunk_pos,
+            index: tile_position_to_index(uvec2(tile_pos.x as u32, tile_pos.y as u32)),
+        }
+    }
+
     pub fn global_position(&self) -> IVec2 {
         tile_index_to_position(self.index)
             + self.chunk_pos * ivec2(CHUNK_SIZE_X as i32, CHUNK_SIZE_Y as i32)
     }
+
+    pub fn shifted(&self, shift: IVec2) -> Self {
+        Self::from_global_position(self.global_position() + shift)
+    }
 }
 
+/// Identifies the rigid body a tile is welded into
+pub type BodyId = u32;
+
+/// Identifies the plant a tile belongs to
+pub type PlantId = usize;
+
 #[derive(Clone, Debug)]
-pub enum TileInfo {
-    Barrier,
-    Sand,
-    Water { priority: HorizontalMove },
+pub struct TileInfo {
+    pub material: MaterialId,
 }
 
 impl TileInfo {
-    pub fn register_move(&mut self, tile_move: TileMoveDirection) {
-        match self {
-            Self::Water { priority } => {
-                if let Some(hor_move) = HorizontalMove::from_tile_move(tile_move) {
-                    *priority = hor_move;
-                }
-            }
-            _ => (),
-        }
+    pub fn new(material: MaterialId) -> Self {
+        Self { material }
     }
 
-    pub fn movement_directions(&self) -> Vec<TileMoveDirection> {
-        match self {
-            TileInfo::Barrier => vec![],
-            TileInfo::Sand => vec![
-                ivec2(0, -1).into(),
-                ivec2(-1, -1).into(),
-                ivec2(1, -1).into(),
-            ],
-            TileInfo::Water { priority } => vec![
-                ivec2(0, -1).into(),
-                ivec2(-1, -1).into(),
-                ivec2(1, -1).into(),
-                priority.to_direction(),
-                priority.opposite().to_direction(),
-            ],
-        }
+    pub fn is_decaying(&self, materials: &MaterialRegistry) -> bool {
+        materials.get(self.material).lifetime.is_some()
+    }
+
+    pub fn is_emitter(&self, materials: &MaterialRegistry) -> bool {
+        materials.get(self.material).emitter.is_some()
+    }
+
+    pub fn is_drain(&self, materials: &MaterialRegistry) -> bool {
+        materials.get(self.material).flags.drain
+    }
+
+    /// Checks whether the tile may move diagonally, tiles that are already
+    /// moving always can, resting ones have to overcome their friction
+    pub fn slides(&self, moving: bool, materials: &MaterialRegistry, roll: f32) -> bool {
+        moving || roll >= materials.get(self.material).friction
     }
 }
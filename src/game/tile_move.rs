@@ -8,8 +8,9 @@ pub trait TileMove {
     fn opposite(&self) -> Self;
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Default)]
 pub enum HorizontalMove {
+    #[default]
     Left,
     Right,
 }
@@ -39,6 +39,10 @@ impl TileMoveDirection {
     pub fn direction(&self) -> IVec2 {
         self.direction
     }
+
+    pub fn is_diagonal(&self) -> bool {
+        self.direction.x != 0 && self.direction.y != 0
+    }
 }
 
 impl From<IVec2> for TileMoveDirection {
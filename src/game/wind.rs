@@ -0,0 +1,45 @@
+use macroquad::prelude::{ivec2, IVec2, Vec2};
+use rayon::prelude::*;
+
+use super::{tile::Tile, World};
+
+impl World {
+    /// Wind at a global tile position, there is none outside of the world
+    pub fn wind_at(&self, position: IVec2) -> Vec2 {
+        let tile = Tile::from_global_position(position);
+        self.chunks
+            .get(&tile.chunk_pos)
+            .map_or(Vec2::ZERO, |chunk| chunk.layers.wind[tile.index])
+    }
+
+    /// Sets the wind of all tiles within a radius and wakes them up,
+    /// so resting tiles start drifting
+    pub fn paint_wind(&mut self, center: IVec2, radius: f32, force: Vec2) {
+        let extent = radius.ceil() as i32;
+        for dx in -extent..=extent {
+            for dy in -extent..=extent {
+                if ((dx * dx + dy * dy) as f32).sqrt() > radius {
+                    continue;
+                }
+
+                let tile = Tile::from_global_position(center + ivec2(dx, dy));
+                if let Some(chunk) = self.chunks.get_mut(&tile.chunk_pos) {
+                    chunk.layers.wind[tile.index] = force;
+                    if chunk.tiles[tile.index] {
+                        chunk.queue_update(tile.index);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Changes the wind of every tile, `animate` gets the global position and the current wind
+    /// of a tile and returns its new wind, tiles aren't woken up by the change
+    pub fn animate_wind(&mut self, animate: impl Fn(IVec2, Vec2) -> Vec2 + Sync) {
+        self.chunks.par_iter_mut().for_each(|(&chunk_pos, chunk)| {
+            for (index, wind) in chunk.layers.wind.iter_mut().enumerate() {
+                *wind = animate(Tile { chunk_pos, index }.global_position(), *wind);
+            }
+        });
+    }
+}
@@ -4,14 +4,27 @@ mod constants;
 mod game;
 mod update_view;
 
-use game::Game;
+use constants::{MATERIALS_PATH, WORLD_PATH};
+use game::{gravity::GravityField, material::MaterialRegistry, Game};
 
 const FIXED_DELTA_TIME: f32 = 1.0 / 30.0;
 const MAX_UPDATES_PER_FRAME: usize = 5;
 
 #[macroquad::main("Tile Physics")]
 async fn main() {
-    let mut game = Game::new();
+    let materials = MaterialRegistry::load(MATERIALS_PATH)
+        .unwrap_or_else(|error| panic!("{}: {}", MATERIALS_PATH, error));
+    let gravity =
+        GravityField::load(WORLD_PATH).unwrap_or_else(|error| panic!("{}: {}", WORLD_PATH, error));
+    // A run can be repeated by passing its seed as the first argument
+    let seed = match std::env::args().nth(1) {
+        Some(seed) => seed
+            .parse()
+            .unwrap_or_else(|error| panic!("seed {}: {}", seed, error)),
+        None => miniquad::date::now() as u64,
+    };
+    println!("Seed: {}", seed);
+    let mut game = Game::new(materials, gravity, seed);
 
     let mut frame_time = 0.0;
     let mut paused = false;
@@ -1,23 +1,23 @@
 use macroquad::prelude::IVec2;
 use std::collections::HashMap;
 
-use crate::game::tile::TileInfo;
+use crate::game::chunk::MovingTile;
 
 #[derive(Default)]
 pub struct UpdateView {
-    tiles: HashMap<IVec2, Option<TileInfo>>,
+    tiles: HashMap<IVec2, Option<MovingTile>>,
 }
 
 impl UpdateView {
-    pub fn into_tiles(self) -> impl Iterator<Item = (IVec2, Option<TileInfo>)> {
+    pub fn into_tiles(self) -> impl Iterator<Item = (IVec2, Option<MovingTile>)> {
         self.tiles.into_iter()
     }
 
-    pub fn update_view(&mut self, tiles: impl Iterator<Item = (IVec2, Option<TileInfo>)>) {
+    pub fn update_view(&mut self, tiles: impl Iterator<Item = (IVec2, Option<MovingTile>)>) {
         self.tiles.extend(tiles);
     }
 
-    pub fn update_tile(&mut self, tile_pos: IVec2, tile: Option<TileInfo>) {
+    pub fn update_tile(&mut self, tile_pos: IVec2, tile: Option<MovingTile>) {
         self.tiles.insert(tile_pos, tile);
     }
 }